@@ -0,0 +1,361 @@
+use crate::{
+    impls::forwarded_items,
+    input::{DelegateArgs, DelegateInput},
+    methods::{assoc_types, forwarded_receiver, returns_impl_trait, ForwardedReceiver},
+};
+use macro_compose::{Collector, Expand};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse_quote,
+    visit_mut::{visit_ident_mut, visit_lifetime_mut, VisitMut},
+    Data, Error, FnArg, GenericParam, Generics, Ident, Index, ItemImpl, ItemTrait, Lifetime,
+    Member, Pat, Result, TraitItem, TraitItemMethod, Type,
+};
+
+/// the call of the macro of the trait replacing the `delegate` and `dispatch` attributes
+pub struct DelegateCallExpand<'a> {
+    pub callback: &'static str,
+    pub item: &'a TokenStream,
+}
+
+impl Expand<DelegateArgs> for DelegateCallExpand<'_> {
+    type Output = TokenStream;
+
+    fn expand(&self, input: &DelegateArgs, _: &mut Collector) -> Option<Self::Output> {
+        let path = &input.path;
+        let callback = format_ident!("{}", self.callback);
+        let item = self.item;
+        Some(quote!(
+            #path! { #callback { #input; #item } }
+        ))
+    }
+}
+
+struct DelegatedImpl {
+    poll_trait: ItemTrait,
+    trait_path: TokenStream,
+    self_ty: Type,
+    generics: Generics,
+    pinned: bool,
+}
+
+impl DelegatedImpl {
+    fn new(input: &DelegateInput) -> Self {
+        // the generic parameters of the trait could clash with the ones of the item
+        let mut poll_trait = input.poll_trait.clone();
+        RenameGenerics::new(&poll_trait).visit_item_trait_mut(&mut poll_trait);
+        let path = &input.args.path;
+        let (_, ty_generics, _) = poll_trait.generics.split_for_impl();
+        let trait_path = quote!(#path #ty_generics);
+
+        let ident = &input.item.ident;
+        let (_, self_generics, _) = input.item.generics.split_for_impl();
+        let self_ty: Type = parse_quote!(#ident #self_generics);
+
+        let mut generics = input.item.generics.clone();
+        for param in poll_trait.generics.params.iter() {
+            match param {
+                GenericParam::Lifetime(_) => {
+                    let index = generics.lifetimes().count();
+                    generics.params.insert(index, param.clone());
+                }
+                _ => generics.params.push(param.clone()),
+            }
+        }
+        let where_clause = generics.make_where_clause();
+        if let Some(trait_where_clause) = &poll_trait.generics.where_clause {
+            where_clause
+                .predicates
+                .extend(trait_where_clause.predicates.iter().cloned());
+        }
+
+        // pinned methods need to move the fields
+        let pinned = poll_trait.items.iter().any(|item| {
+            matches!(item, TraitItem::Method(m)
+                if m.default.is_none() && forwarded_receiver(m, true) == Some(ForwardedReceiver::Pinned))
+        });
+        if pinned {
+            where_clause
+                .predicates
+                .push(parse_quote!(#self_ty: ::core::marker::Unpin));
+        }
+
+        DelegatedImpl {
+            poll_trait,
+            trait_path,
+            self_ty,
+            generics,
+            pinned,
+        }
+    }
+
+    fn bound(&mut self, ty: &Type, trait_path: TokenStream) {
+        let where_clause = self.generics.make_where_clause();
+        where_clause.predicates.push(parse_quote!(#ty: #trait_path));
+        if self.pinned {
+            where_clause
+                .predicates
+                .push(parse_quote!(#ty: ::core::marker::Unpin));
+        }
+    }
+
+    fn receiver(&self, method: &TraitItemMethod) -> Option<ForwardedReceiver> {
+        // provided methods can keep their default, required ones are checked by the lints
+        forwarded_receiver(method, true)
+            .filter(|receiver| self.pinned || *receiver != ForwardedReceiver::Pinned)
+    }
+
+    fn finish(self, items: Vec<TokenStream>) -> ItemImpl {
+        let trait_path = &self.trait_path;
+        let self_ty = &self.self_ty;
+        let (impl_generics, _, where_clause) = self.generics.split_for_impl();
+        parse_quote!(
+            impl #impl_generics #trait_path for #self_ty #where_clause {
+                #(#items)*
+            }
+        )
+    }
+}
+
+/// implements the poll trait for a struct by calling the implementation of one of its fields
+pub struct ImplDelegateExpand;
+
+impl Expand<DelegateInput> for ImplDelegateExpand {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &DelegateInput, _: &mut Collector) -> Option<Self::Output> {
+        let (member, field_ty) = delegated_field(input).ok()?;
+
+        let mut delegated = DelegatedImpl::new(input);
+        delegated.bound(field_ty, delegated.trait_path.clone());
+
+        let trait_path = delegated.trait_path.clone();
+        let items = forwarded_items(
+            delegated.poll_trait.clone(),
+            &trait_path,
+            field_ty,
+            |m, call| {
+                let this = self_token(m);
+                let receiver = match delegated.receiver(m)? {
+                    ForwardedReceiver::Shared => parse_quote!(&#this.#member),
+                    ForwardedReceiver::Unique => parse_quote!(&mut #this.#member),
+                    ForwardedReceiver::Pinned => parse_quote!(
+                        ::core::pin::Pin::new(&mut ::core::pin::Pin::get_mut(#this).#member)
+                    ),
+                };
+                Some(call.to(field_ty, receiver))
+            },
+        );
+
+        Some(delegated.finish(items))
+    }
+}
+
+/// implements the poll trait for an enum by matching on its variants
+pub struct ImplDispatchExpand;
+
+impl Expand<DelegateInput> for ImplDispatchExpand {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &DelegateInput, _: &mut Collector) -> Option<Self::Output> {
+        let variants = dispatched_variants(input).ok()?;
+        let (_, _, first_ty) = variants.first()?;
+
+        let mut delegated = DelegatedImpl::new(input);
+
+        // the associated types are the ones of the first variant, the others have to match
+        let path = &input.args.path;
+        let trait_path = delegated.trait_path.clone();
+        let args = delegated
+            .poll_trait
+            .generics
+            .params
+            .iter()
+            .map(|param| match param {
+                GenericParam::Type(tp) => tp.ident.to_token_stream(),
+                GenericParam::Lifetime(ld) => ld.lifetime.to_token_stream(),
+                GenericParam::Const(cp) => cp.ident.to_token_stream(),
+            });
+        let bindings = assoc_types(&delegated.poll_trait).map(|ty| {
+            let ident = &ty.ident;
+            quote!(#ident = <#first_ty as #trait_path>::#ident)
+        });
+        let bound_path = quote!(#path<#(#args,)* #(#bindings),*>);
+        for (i, (_, _, ty)) in variants.iter().enumerate() {
+            let bound = if i == 0 {
+                trait_path.clone()
+            } else {
+                bound_path.clone()
+            };
+            delegated.bound(ty, bound);
+        }
+
+        let items = forwarded_items(
+            delegated.poll_trait.clone(),
+            &trait_path,
+            first_ty,
+            |m, call| {
+                // every variant returns a different `impl Trait`, keep the default
+                if returns_impl_trait(m) {
+                    return None;
+                }
+
+                let this = self_token(m);
+                let receiver = delegated.receiver(m)?;
+                let arms = variants.iter().map(|(variant, member, ty)| {
+                    let pat = match member {
+                        Some(member) => quote!(Self::#variant { #member: __inner }),
+                        None => quote!(Self::#variant(__inner)),
+                    };
+                    let call = match receiver {
+                        ForwardedReceiver::Pinned => {
+                            call.to(ty, parse_quote!(::core::pin::Pin::new(__inner)))
+                        }
+                        _ => call.to(ty, parse_quote!(__inner)),
+                    };
+                    quote!(#pat => #call,)
+                });
+                let scrutinee = match receiver {
+                    ForwardedReceiver::Pinned => quote!(::core::pin::Pin::get_mut(#this)),
+                    _ => quote!(#this),
+                };
+                Some(parse_quote!(match #scrutinee { #(#arms)* }))
+            },
+        );
+
+        Some(delegated.finish(items))
+    }
+}
+
+/// the field passed as `to = field`, or the only field of the struct
+pub fn delegated_field(input: &DelegateInput) -> Result<(Member, &Type)> {
+    let fields = match &input.item.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.item.ident,
+                "only structs can delegate to one of their fields, use `dispatch` for enums",
+            ))
+        }
+    };
+
+    let (member, field) = match &input.args.to {
+        Some(Member::Named(ident)) => (
+            Member::Named(ident.clone()),
+            fields
+                .iter()
+                .find(|field| field.ident.as_ref() == Some(ident)),
+        ),
+        Some(Member::Unnamed(index)) => (
+            Member::Unnamed(index.clone()),
+            fields
+                .iter()
+                .nth(index.index as usize)
+                .filter(|field| field.ident.is_none()),
+        ),
+        None if fields.len() == 1 => {
+            let field = fields.iter().next().unwrap();
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index {
+                    index: 0,
+                    span: Span::call_site(),
+                }),
+            };
+            (member, Some(field))
+        }
+        None => {
+            return Err(Error::new_spanned(
+                &input.args.path,
+                "expected `to = field`, the struct doesn't have exactly one field",
+            ))
+        }
+    };
+
+    match field {
+        Some(field) => Ok((member, &field.ty)),
+        None => Err(Error::new_spanned(member, "no such field")),
+    }
+}
+
+pub fn dispatched_variants(input: &DelegateInput) -> Result<Vec<(&Ident, Option<&Ident>, &Type)>> {
+    let variants = match &input.item.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.item.ident,
+                "only enums can dispatch to their variants, use `delegate` for structs",
+            ))
+        }
+    };
+    if variants.is_empty() {
+        return Err(Error::new_spanned(
+            &input.item.ident,
+            "the enum needs at least one variant to dispatch to",
+        ));
+    }
+
+    variants
+        .iter()
+        .map(
+            |variant| match variant.fields.iter().collect::<Vec<_>>()[..] {
+                [field] => Ok((&variant.ident, field.ident.as_ref(), &field.ty)),
+                _ => Err(Error::new_spanned(
+                    variant,
+                    "dispatched variants need exactly one field",
+                )),
+            },
+        )
+        .collect()
+}
+
+/// the `self` of the receiver, which has the hygiene of the signature
+fn self_token(method: &TraitItemMethod) -> TokenStream {
+    match method.sig.inputs.first() {
+        Some(FnArg::Receiver(r)) => r.self_token.to_token_stream(),
+        Some(FnArg::Typed(pt)) => match &*pt.pat {
+            Pat::Ident(pi) => pi.ident.to_token_stream(),
+            _ => quote!(self),
+        },
+        None => quote!(self),
+    }
+}
+
+/// renames the generic parameters of the trait to `__T` and `'__a`
+struct RenameGenerics {
+    idents: Vec<Ident>,
+    lifetimes: Vec<Ident>,
+}
+
+impl RenameGenerics {
+    fn new(item: &ItemTrait) -> Self {
+        let mut idents = Vec::new();
+        let mut lifetimes = Vec::new();
+        for param in item.generics.params.iter() {
+            match param {
+                GenericParam::Type(tp) => idents.push(tp.ident.clone()),
+                GenericParam::Const(cp) => idents.push(cp.ident.clone()),
+                GenericParam::Lifetime(ld) => lifetimes.push(ld.lifetime.ident.clone()),
+            }
+        }
+        RenameGenerics { idents, lifetimes }
+    }
+}
+
+impl VisitMut for RenameGenerics {
+    fn visit_ident_mut(&mut self, i: &mut Ident) {
+        if self.idents.contains(i) {
+            *i = format_ident!("__{}", i, span = i.span());
+        }
+        visit_ident_mut(self, i);
+    }
+
+    fn visit_lifetime_mut(&mut self, i: &mut Lifetime) {
+        if self.lifetimes.contains(&i.ident) {
+            i.ident = format_ident!("__{}", i.ident, span = i.ident.span());
+        } else {
+            visit_lifetime_mut(self, i);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    delegate::{delegated_field, dispatched_variants},
+    input::{DelegateArgs, DelegateInput},
+    methods::{forwarded_receiver, returns_impl_trait},
+};
+use macro_compose::{Collector, Lint};
+use syn::{Error, TraitItem};
+
+pub struct DelegateArgsLint;
+
+impl Lint<DelegateArgs> for DelegateArgsLint {
+    fn lint(&self, input: &DelegateArgs, c: &mut Collector) {
+        // the path is also the path of the macro of the trait
+        for segment in input.path.segments.iter() {
+            if !segment.arguments.is_empty() {
+                c.error(Error::new_spanned(
+                    &segment.arguments,
+                    "the trait is implemented for all of its generic arguments, leave them out",
+                ));
+            }
+        }
+    }
+}
+
+pub struct DispatchArgsLint;
+
+impl Lint<DelegateArgs> for DispatchArgsLint {
+    fn lint(&self, input: &DelegateArgs, c: &mut Collector) {
+        if let Some(to) = &input.to {
+            c.error(Error::new_spanned(
+                to,
+                "enums dispatch to all of their variants, `to` is only used by `delegate`",
+            ));
+        }
+    }
+}
+
+pub struct DelegateLint;
+
+impl Lint<DelegateInput> for DelegateLint {
+    fn lint(&self, input: &DelegateInput, c: &mut Collector) {
+        if let Err(e) = delegated_field(input) {
+            c.error(e);
+        }
+
+        // provided methods keep their default when they can't be delegated
+        for item in input.poll_trait.items.iter() {
+            if let TraitItem::Method(m) = item {
+                if m.default.is_none() && forwarded_receiver(m, true).is_none() {
+                    c.error(Error::new_spanned(
+                        &input.args.path,
+                        format!(
+                            "`{}` can't be delegated, it has to take `&self`, `&mut self` or `self: Pin<&mut Self>` and can only use `Self` for associated types otherwise",
+                            m.sig.ident
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+pub struct DispatchLint;
+
+impl Lint<DelegateInput> for DispatchLint {
+    fn lint(&self, input: &DelegateInput, c: &mut Collector) {
+        if let Err(e) = dispatched_variants(input) {
+            c.error(e);
+        }
+
+        for item in input.poll_trait.items.iter() {
+            match item {
+                TraitItem::Const(ct) => c.error(Error::new_spanned(
+                    &input.args.path,
+                    format!(
+                        "`{}` can't be dispatched, the variants could have different values for associated consts",
+                        ct.ident
+                    ),
+                )),
+                TraitItem::Type(ty) if !ty.generics.params.is_empty() => {
+                    c.error(Error::new_spanned(
+                        &input.args.path,
+                        format!(
+                            "`{}` can't be dispatched, the variants have different generic associated types",
+                            ty.ident
+                        ),
+                    ))
+                }
+                // provided methods keep their default when they can't be dispatched
+                TraitItem::Method(m) if m.default.is_none() => {
+                    if forwarded_receiver(m, true).is_none() {
+                        c.error(Error::new_spanned(
+                            &input.args.path,
+                            format!(
+                                "`{}` can't be dispatched, it has to take `&self`, `&mut self` or `self: Pin<&mut Self>` and can only use `Self` for associated types otherwise",
+                                m.sig.ident
+                            ),
+                        ));
+                    } else if returns_impl_trait(m) {
+                        c.error(Error::new_spanned(
+                            &input.args.path,
+                            format!(
+                                "`{}` can't be dispatched, the variants return different `impl Trait` types",
+                                m.sig.ident
+                            ),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod expand;
+mod lint;
+
+pub use expand::*;
+pub use lint::*;
@@ -1,11 +1,23 @@
 use crate::{
     ext_trait_name,
+    input::{Forward, TraitInput},
     methods::{
-        DynamicProvidedMethodImplExpand, MethodExtImplExpand, StaticProvidedMethodImplExpand,
+        assoc_type_param, assoc_types, desugar_impl_trait, elide_lifetimes, forward_args,
+        forwarded_receiver, is_gat_method, qualify_calls, trait_bound, turbofish,
+        with_owned_variant, DynMethodImplExpand, DynamicProvidedMethodImplExpand,
+        ForwardedReceiver, MethodExtImplExpand, NativeMethodImplExpand,
+        StateMachineMethodImplExpand, StaticProvidedMethodImplExpand,
+        TraitObjectProvidedMethodImplExpand, UnpinMethodExtImplExpand,
     },
+    traits::PollTraitExpand,
 };
 use macro_compose::{Collector, Context, Expand};
-use syn::{parse_quote, ItemImpl, ItemTrait, TraitItem};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse_quote, Expr, GenericParam, Ident, ItemImpl, ItemTrait, TraitItem, TraitItemMethod, Type,
+    TypeParam, TypeParamBound, WherePredicate,
+};
 
 pub struct ImplExtTraitExpand;
 
@@ -13,32 +25,374 @@ impl Expand<ItemTrait> for ImplExtTraitExpand {
     type Output = ItemImpl;
 
     fn expand(&self, input: &ItemTrait, c: &mut Collector) -> Option<Self::Output> {
+        let methods = ext_methods(input, c, false);
+
+        let trait_ident = &input.ident;
+        let ext_ident = ext_trait_name(input);
+
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+        let mut generics = input.generics.clone();
+        generics
+            .params
+            .push(parse_quote!(__IMPL: #trait_ident #ty_generics));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        Some(parse_quote!(
+            impl #impl_generics #ext_ident #ty_generics for __IMPL #where_clause {
+                #(#methods)*
+            }
+        ))
+    }
+}
+
+/// implements the extension trait for the trait objects of dynamic traits
+pub struct ImplTraitObjectExtExpand {
+    pub send: bool,
+    pub sync: bool,
+}
+
+impl Expand<ItemTrait> for ImplTraitObjectExtExpand {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &ItemTrait, _: &mut Collector) -> Option<Self::Output> {
+        TraitInput::from(input.attrs.as_slice()).dynamic?;
+
+        // the blanket implementation already reported the errors
+        let methods = ext_methods(input, &mut Collector::new(), true)
+            .into_iter()
+            .filter(|m| !requires_sized(m));
+
+        let ext_ident = ext_trait_name(input);
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+        let mut generics = input.generics.clone();
+        generics.params.insert(0, parse_quote!('__dyn));
+        generics
+            .params
+            .extend(assoc_types(input).map(|ty| -> GenericParam {
+                let mut param = TypeParam::from(assoc_type_param(&ty.ident));
+                param.colon_token = ty.colon_token;
+                param.bounds = ty.bounds.clone();
+                GenericParam::Type(param)
+            }));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        let trait_bound = trait_bound(input, &input.ident);
+        let send = if self.send {
+            Some(quote!(+ ::core::marker::Send))
+        } else {
+            None
+        };
+        let sync = if self.sync {
+            Some(quote!(+ ::core::marker::Sync))
+        } else {
+            None
+        };
+
+        Some(parse_quote!(
+            impl #impl_generics #ext_ident #ty_generics for dyn #trait_bound #send #sync + '__dyn #where_clause {
+                #(#methods)*
+            }
+        ))
+    }
+}
+
+fn ext_methods(input: &ItemTrait, c: &mut Collector, trait_object: bool) -> Vec<TraitItemMethod> {
+    input
+        .items
+        .iter()
+        .cloned()
+        .flat_map(with_owned_variant)
+        .map(elide_lifetimes)
+        .map(desugar_impl_trait)
+        .map(|item| qualify_calls(input, item))
+        .filter_map(|i| match i {
+            TraitItem::Method(m) if !is_gat_method(input, &m) => Some(m),
+            _ => None,
+        })
+        .flat_map(|m| {
+            let mut subcontext = Context::new(c, m);
+            if trait_object {
+                if let Some(m) = subcontext.capture(&TraitObjectProvidedMethodImplExpand(input)) {
+                    return vec![m];
+                }
+            }
+            subcontext
+                .capture(&MethodExtImplExpand(input))
+                .into_iter()
+                .chain(subcontext.capture(&UnpinMethodExtImplExpand(input)))
+                .chain(subcontext.capture(&StaticProvidedMethodImplExpand(input)))
+                .chain(subcontext.capture(&DynamicProvidedMethodImplExpand(input)))
+                .chain(subcontext.capture(&StateMachineMethodImplExpand(input)))
+                .collect()
+        })
+        .collect()
+}
+
+fn requires_sized(method: &TraitItemMethod) -> bool {
+    let where_clause = match &method.sig.generics.where_clause {
+        Some(where_clause) => where_clause,
+        None => return false,
+    };
+    where_clause.predicates.iter().any(|predicate| match predicate {
+        WherePredicate::Type(pt) => {
+            matches!(&pt.bounded_ty, Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident("Self"))
+                && pt.bounds.iter().any(|bound| {
+                    matches!(bound, TypeParamBound::Trait(tb)
+                        if tb.path.segments.last().is_some_and(|s| s.ident == "Sized"))
+                })
+        }
+        _ => false,
+    })
+}
+
+/// the blanket implementation of the native trait for all implementors of the trait
+pub struct ImplNativeTraitExpand;
+
+impl Expand<ItemTrait> for ImplNativeTraitExpand {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &ItemTrait, c: &mut Collector) -> Option<Self::Output> {
+        TraitInput::from(input.attrs.as_slice()).native?;
+        let native_ident = TraitInput::native(&input.attrs)?.ok()?;
+        let trait_ident = &input.ident;
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+
+        let types = input.items.iter().filter_map(|item| match item {
+            TraitItem::Type(ty) => {
+                let ident = &ty.ident;
+                let (params, args, where_clause) = ty.generics.split_for_impl();
+                Some(quote!(
+                    type #ident #params = <__IMPL as #trait_ident #ty_generics>::#ident #args #where_clause;
+                ))
+            }
+            _ => None,
+        });
+
         let methods = input
             .items
             .iter()
-            .filter_map(|i| {
-                if let TraitItem::Method(m) = i {
-                    Some(m.clone())
-                } else {
-                    None
+            .cloned()
+            .flat_map(with_owned_variant)
+            .map(elide_lifetimes)
+            .map(desugar_impl_trait)
+            .filter_map(|i| match i {
+                TraitItem::Method(m) => {
+                    let mut subcontext = Context::new(c, m);
+                    subcontext.capture(&NativeMethodImplExpand(input))
                 }
-            })
-            .flat_map(|m| {
-                let mut subcontext = Context::new(c, m);
-                subcontext
-                    .capture(&MethodExtImplExpand(input))
-                    .into_iter()
-                    .chain(subcontext.capture(&StaticProvidedMethodImplExpand(input)))
-                    .chain(subcontext.capture(&DynamicProvidedMethodImplExpand(input)))
+                _ => None,
             });
 
+        let mut generics = input.generics.clone();
+        generics
+            .params
+            .push(parse_quote!(__IMPL: #trait_ident #ty_generics));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        Some(parse_quote!(
+            impl #impl_generics #native_ident #ty_generics for __IMPL #where_clause {
+                #(#types)*
+                #(#methods)*
+            }
+        ))
+    }
+}
+
+pub struct ImplDynTraitExpand;
+
+impl Expand<ItemTrait> for ImplDynTraitExpand {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &ItemTrait, c: &mut Collector) -> Option<Self::Output> {
+        TraitInput::from(input.attrs.as_slice()).dyn_trait?;
+        let dyn_ident = TraitInput::dyn_trait(&input.attrs)?.ok()?;
         let trait_ident = &input.ident;
-        let ext_ident = ext_trait_name(input);
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+
+        let types = input.items.iter().filter_map(|item| match item {
+            TraitItem::Type(ty) => {
+                let ident = &ty.ident;
+                let (params, args, where_clause) = ty.generics.split_for_impl();
+                Some(quote!(
+                    type #ident #params = <__IMPL as #trait_ident #ty_generics>::#ident #args #where_clause;
+                ))
+            }
+            _ => None,
+        });
+
+        let methods = input
+            .items
+            .iter()
+            .cloned()
+            .flat_map(with_owned_variant)
+            .map(elide_lifetimes)
+            .map(desugar_impl_trait)
+            .filter_map(|i| match i {
+                TraitItem::Method(m) => {
+                    let mut subcontext = Context::new(c, m);
+                    subcontext.capture(&DynMethodImplExpand(input))
+                }
+                _ => None,
+            });
+
+        let mut generics = input.generics.clone();
+        generics
+            .params
+            .push(parse_quote!(__IMPL: #trait_ident #ty_generics));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
 
         Some(parse_quote!(
-            impl<__IMPL: #trait_ident> #ext_ident for __IMPL {
+            impl #impl_generics #dyn_ident #ty_generics for __IMPL #where_clause {
+                #(#types)*
                 #(#methods)*
             }
         ))
     }
 }
+
+/// implements the poll trait for a pointer to an implementor
+pub struct ImplForwardExpand(pub Forward);
+
+impl Expand<ItemTrait> for ImplForwardExpand {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &ItemTrait, c: &mut Collector) -> Option<Self::Output> {
+        TraitInput::from(input.attrs.as_slice()).forward?;
+        if !TraitInput::forward(&input.attrs)?.ok()?.contains(&self.0) {
+            return None;
+        }
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let poll_trait = ctx.capture(&PollTraitExpand)?;
+        let trait_ident = &input.ident;
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+
+        // pinned methods need to move the pointer
+        let pinned = poll_trait.items.iter().any(|item| {
+            matches!(item, TraitItem::Method(m)
+                if m.default.is_none() && forwarded_receiver(m, false) == Some(ForwardedReceiver::Pinned))
+        });
+
+        let mut generics = input.generics.clone();
+        let (self_ty, target): (Type, Type) = match self.0 {
+            Forward::Pin => {
+                generics.params.push(if pinned {
+                    parse_quote!(__P: ::core::ops::DerefMut + ::core::marker::Unpin)
+                } else {
+                    parse_quote!(__P: ::core::ops::Deref)
+                });
+                let target: Type = parse_quote!(<__P as ::core::ops::Deref>::Target);
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(parse_quote!(#target: #trait_ident #ty_generics));
+                (parse_quote!(::core::pin::Pin<__P>), target)
+            }
+            forward => {
+                generics.params.push(if pinned {
+                    parse_quote!(__T: ?::core::marker::Sized + ::core::marker::Unpin + #trait_ident #ty_generics)
+                } else {
+                    parse_quote!(__T: ?::core::marker::Sized + #trait_ident #ty_generics)
+                });
+                let self_ty = match forward {
+                    Forward::RefMut => parse_quote!(&mut __T),
+                    Forward::Ref => parse_quote!(&__T),
+                    Forward::Box => parse_quote!(::std::boxed::Box<__T>),
+                    Forward::Rc => parse_quote!(::std::rc::Rc<__T>),
+                    Forward::Arc => parse_quote!(::std::sync::Arc<__T>),
+                    Forward::Pin => unreachable!(),
+                };
+                (self_ty, parse_quote!(__T))
+            }
+        };
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        let trait_path = quote!(#trait_ident #ty_generics);
+        let items = forwarded_items(poll_trait, &trait_path, &target, |m, call| {
+            // provided methods can keep their default, required ones are checked by the lint
+            let receiver = forwarded_receiver(m, false)
+                .filter(|receiver| receiver.forwarded_through(self.0))
+                .filter(|receiver| pinned || *receiver != ForwardedReceiver::Pinned)?;
+            let receiver = match (receiver, self.0) {
+                (ForwardedReceiver::Shared, _) => parse_quote!(&**self),
+                (ForwardedReceiver::Unique, _) => parse_quote!(&mut **self),
+                (ForwardedReceiver::Pinned, Forward::Pin) => {
+                    parse_quote!(::core::pin::Pin::get_mut(self).as_mut())
+                }
+                (ForwardedReceiver::Pinned, _) => parse_quote!(::core::pin::Pin::new(
+                    &mut **::core::pin::Pin::get_mut(self)
+                )),
+            };
+            Some(call.to(&target, receiver))
+        });
+
+        Some(parse_quote!(
+            impl #impl_generics #trait_ident #ty_generics for #self_ty #where_clause {
+                #(#items)*
+            }
+        ))
+    }
+}
+
+/// the items of an implementation of the poll trait calling other implementations
+pub fn forwarded_items(
+    poll_trait: ItemTrait,
+    trait_path: &TokenStream,
+    target: &Type,
+    body: impl Fn(&TraitItemMethod, &ForwardedCall) -> Option<Expr>,
+) -> Vec<TokenStream> {
+    poll_trait
+        .items
+        .into_iter()
+        .filter_map(|item| match item {
+            TraitItem::Type(ty) => {
+                let attrs = &ty.attrs;
+                let ident = &ty.ident;
+                let (params, args, where_clause) = ty.generics.split_for_impl();
+                Some(quote!(
+                    #(#attrs)*
+                    type #ident #params = <#target as #trait_path>::#ident #args #where_clause;
+                ))
+            }
+            TraitItem::Const(item) => {
+                let attrs = &item.attrs;
+                let ident = &item.ident;
+                let ty = &item.ty;
+                Some(quote!(
+                    #(#attrs)*
+                    const #ident: #ty = <#target as #trait_path>::#ident;
+                ))
+            }
+            TraitItem::Method(mut m) => {
+                let call = ForwardedCall {
+                    trait_path,
+                    ident: m.sig.ident.clone(),
+                    turbofish: turbofish(&m.sig.generics),
+                    args: forward_args(&mut m).into_iter().skip(1).collect(),
+                };
+                let body = body(&m, &call)?;
+                m.default = Some(parse_quote!({ #body }));
+                m.semi_token = None;
+                Some(quote!(#m))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+pub struct ForwardedCall<'a> {
+    trait_path: &'a TokenStream,
+    ident: Ident,
+    turbofish: Option<TokenStream>,
+    args: Vec<Expr>,
+}
+
+impl ForwardedCall<'_> {
+    pub fn to(&self, target: &Type, receiver: Expr) -> Expr {
+        let trait_path = self.trait_path;
+        let ident = &self.ident;
+        let turbofish = &self.turbofish;
+        let args = &self.args;
+        parse_quote!(<#target as #trait_path>::#ident #turbofish(#receiver, #(#args),*))
+    }
+}
@@ -1,12 +1,299 @@
-use macro_input::MacroInput;
+use macro_input::{FieldDef, MacroInput};
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use std::fmt;
+use syn::{
+    braced,
+    parse::{Parse, ParseStream, Parser},
+    punctuated::Punctuated,
+    Attribute, DeriveInput, Error, Ident, ItemTrait, Lit, LitStr, Member, Path, Result, Token,
+    Type,
+};
+
 #[derive(MacroInput, Debug)]
 #[macro_input(rename = "async_trait_ext")]
 pub struct TraitInput {
     pub dynamic: Option<()>,
+    pub send: Option<()>,
+    pub sync: Option<()>,
+    pub fused: Option<()>,
+    pub futures_core: Option<()>,
+    pub rpitit: Option<()>,
+    pub gat: Option<()>,
+    pub native: Option<String>,
+    pub dyn_trait: Option<String>,
+    pub forward: Option<String>,
+    pub delegate: Option<()>,
+    pub ext: Option<String>,
+    pub future: Option<String>,
+}
+
+impl TraitInput {
+    /// remove all `#[async_trait_ext(..)]` attributes, unlike `strip` also with multiple fields
+    pub fn remove(attrs: &mut Vec<Attribute>) {
+        attrs.retain(|attr| !attr.path.is_ident("async_trait_ext"));
+    }
+
+    /// flatten `dynamic(send, sync)`, `fused(futures_core)` and `forward(&mut, Box)`, the fields
+    /// of `TraitInput` can't be nested
+    pub fn flatten(args: TokenStream) -> Result<TokenStream> {
+        let mut tokens = args.into_iter().peekable();
+        let mut flattened = Vec::new();
+        while tokens.peek().is_some() {
+            let arg: Vec<_> = tokens
+                .by_ref()
+                .take_while(|tt| !matches!(tt, TokenTree::Punct(p) if p.as_char() == ','))
+                .collect();
+
+            flattened.push(match arg.as_slice() {
+                [TokenTree::Ident(ident), TokenTree::Group(group)]
+                    if ident == "dynamic" && group.delimiter() == Delimiter::Parenthesis =>
+                {
+                    let options =
+                        Punctuated::<Ident, Token![,]>::parse_terminated.parse2(group.stream())?;
+                    if let Some(option) = options
+                        .iter()
+                        .find(|option| *option != "send" && *option != "sync")
+                    {
+                        return Err(Error::new_spanned(option, "expected `send` or `sync`"));
+                    }
+                    let options = options.iter();
+                    quote!(#ident #(, #options)*)
+                }
+                [TokenTree::Ident(ident), TokenTree::Group(group)]
+                    if ident == "fused" && group.delimiter() == Delimiter::Parenthesis =>
+                {
+                    let options =
+                        Punctuated::<Ident, Token![,]>::parse_terminated.parse2(group.stream())?;
+                    if let Some(option) = options.iter().find(|option| *option != "futures_core") {
+                        return Err(Error::new_spanned(option, "expected `futures_core`"));
+                    }
+                    let options = options.iter();
+                    quote!(#ident #(, #options)*)
+                }
+                [TokenTree::Ident(ident), TokenTree::Group(group)]
+                    if ident == "forward" && group.delimiter() == Delimiter::Parenthesis =>
+                {
+                    let pointers = LitStr::new(&group.stream().to_string(), group.span());
+                    quote!(#ident = #pointers)
+                }
+                // let the lints report malformed arguments
+                arg => arg.iter().cloned().collect(),
+            });
+        }
+
+        Ok(quote!(#(#flattened),*))
+    }
+
+    /// parse the name passed as `native = "..."`
+    pub fn native(attrs: &[Attribute]) -> Option<Result<Ident>> {
+        parse_field(&__trait_input::NATIVE_FIELD, attrs)
+    }
+
+    /// parse the name passed as `dyn_trait = "..."`
+    pub fn dyn_trait(attrs: &[Attribute]) -> Option<Result<Ident>> {
+        parse_field(&__trait_input::DYN_TRAIT_FIELD, attrs)
+    }
+
+    /// parse the name passed as `ext = "..."`
+    pub fn ext(attrs: &[Attribute]) -> Option<Result<Ident>> {
+        parse_field(&__trait_input::EXT_FIELD, attrs)
+    }
+
+    /// the template passed as `future = "..."`, with `{trait}` and `{method}` placeholders
+    pub fn future(attrs: &[Attribute]) -> Option<Result<String>> {
+        match __trait_input::FUTURE_FIELD.get_lit(attrs)? {
+            Lit::Str(lit) => {
+                let template = lit.value();
+                let example = template
+                    .replace("{trait}", "Trait")
+                    .replace("{method}", "Method");
+                Some(if !template.contains("{method}") {
+                    Err(Error::new_spanned(
+                        lit,
+                        "the template needs `{method}`, the futures would share a name otherwise",
+                    ))
+                } else if syn::parse_str::<Ident>(&example).is_err() {
+                    Err(Error::new_spanned(
+                        lit,
+                        "the template has to be an identifier with `{trait}` and `{method}`",
+                    ))
+                } else {
+                    Ok(template)
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// parse the pointers passed as `forward(...)`
+    pub fn forward(attrs: &[Attribute]) -> Option<Result<Vec<Forward>>> {
+        match __trait_input::FORWARD_FIELD.get_lit(attrs)? {
+            Lit::Str(lit) => Some(
+                lit.parse_with(Punctuated::<Forward, Token![,]>::parse_terminated)
+                    .map(|pointers| pointers.into_iter().collect()),
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// a pointer the poll trait is implemented for, see `#[async_trait_ext(forward(...))]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forward {
+    RefMut,
+    Ref,
+    Box,
+    Rc,
+    Arc,
+    Pin,
+}
+
+impl Forward {
+    pub const ALL: [Forward; 6] = [
+        Forward::RefMut,
+        Forward::Ref,
+        Forward::Box,
+        Forward::Rc,
+        Forward::Arc,
+        Forward::Pin,
+    ];
+}
+
+impl Parse for Forward {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.parse::<Option<Token![&]>>()?.is_some() {
+            return Ok(if input.parse::<Option<Token![mut]>>()?.is_some() {
+                Forward::RefMut
+            } else {
+                Forward::Ref
+            });
+        }
+
+        let ident = input.parse::<Ident>()?;
+        match ident.to_string().as_str() {
+            "Box" => Ok(Forward::Box),
+            "Rc" => Ok(Forward::Rc),
+            "Arc" => Ok(Forward::Arc),
+            "Pin" => Ok(Forward::Pin),
+            _ => Err(Error::new_spanned(
+                ident,
+                "expected `&mut`, `&`, `Box`, `Rc`, `Arc` or `Pin`",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Forward {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Forward::RefMut => "&mut",
+            Forward::Ref => "&",
+            Forward::Box => "Box",
+            Forward::Rc => "Rc",
+            Forward::Arc => "Arc",
+            Forward::Pin => "Pin",
+        })
+    }
 }
 
 #[derive(MacroInput, Debug)]
 #[macro_input(rename = "async_fn")]
 pub struct MethodInput {
     pub provided: Option<()>,
+    pub take_args: Option<()>,
+    pub state: Option<String>,
+    pub on_cancel: Option<()>,
+    pub owned: Option<()>,
+    pub boxed: Option<()>,
+    pub boxed_local: Option<()>,
+    pub state_machine: Option<()>,
+    pub overridable: Option<()>,
+    pub poll: Option<String>,
+    pub future: Option<String>,
+}
+
+impl MethodInput {
+    /// remove all `#[async_fn(..)]` attributes, unlike `strip` also with multiple fields
+    pub fn remove(attrs: &mut Vec<Attribute>) {
+        attrs.retain(|attr| !attr.path.is_ident("async_fn"));
+    }
+
+    /// parse the type passed as `state = "..."`
+    pub fn state(attrs: &[Attribute]) -> Option<Result<Type>> {
+        parse_field(&__method_input::STATE_FIELD, attrs)
+    }
+
+    /// parse the name passed as `poll = "..."`
+    pub fn poll(attrs: &[Attribute]) -> Option<Result<Ident>> {
+        parse_field(&__method_input::POLL_FIELD, attrs)
+    }
+
+    /// parse the name passed as `future = "..."`
+    pub fn future(attrs: &[Attribute]) -> Option<Result<Ident>> {
+        parse_field(&__method_input::FUTURE_FIELD, attrs)
+    }
+}
+
+/// parse the string literal of a field
+fn parse_field<T: Parse>(field: &FieldDef, attrs: &[Attribute]) -> Option<Result<T>> {
+    match field.get_lit(attrs)? {
+        Lit::Str(lit) => Some(lit.parse()),
+        _ => unreachable!(),
+    }
+}
+
+/// the arguments of `#[async_trait_ext::delegate(Trait, to = field)]`
+pub struct DelegateArgs {
+    pub path: Path,
+    pub to: Option<Member>,
+}
+
+impl Parse for DelegateArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let path = input.parse()?;
+        let mut to = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && input.peek(Ident) {
+            let ident = input.parse::<Ident>()?;
+            if ident != "to" {
+                return Err(Error::new_spanned(ident, "expected `to`"));
+            }
+            input.parse::<Token![=]>()?;
+            to = Some(input.parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(DelegateArgs { path, to })
+    }
+}
+
+impl ToTokens for DelegateArgs {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.path.to_tokens(tokens);
+        if let Some(to) = &self.to {
+            tokens.extend(quote!(, to = #to));
+        }
+    }
+}
+
+/// the input of the hidden `__delegate!` macro: the poll trait, the arguments and the item
+pub struct DelegateInput {
+    pub poll_trait: ItemTrait,
+    pub args: DelegateArgs,
+    pub item: DeriveInput,
+}
+
+impl Parse for DelegateInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        braced!(content in input);
+        let poll_trait = content.parse()?;
+        let args = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let item = input.parse()?;
+        Ok(DelegateInput {
+            poll_trait,
+            args,
+            item,
+        })
+    }
 }
@@ -1,3 +1,4 @@
+mod delegate;
 mod impls;
 mod input;
 mod methods;
@@ -12,21 +13,117 @@ use syn::{parse_quote, Ident, ItemTrait};
 pub fn async_trait_ext(input: TokenStream, item: TokenStream) -> TokenStream {
     let input: proc_macro2::TokenStream = input.into();
     let item: proc_macro2::TokenStream = item.into();
-    let combined: proc_macro2::TokenStream = parse_quote!(#[async_trait_ext( #input )] #item);
 
     let mut collector = Collector::new();
 
+    let input = match input::TraitInput::flatten(input) {
+        Ok(input) => input,
+        Err(e) => {
+            collector.error(e);
+            return collector.finish().into();
+        }
+    };
+    let combined: proc_macro2::TokenStream = parse_quote!(#[async_trait_ext( #input )] #item);
+
     let mut trait_context = Context::<ItemTrait>::new_parse2(&mut collector, combined);
     trait_context.lint(&traits::AttributeLint);
+    trait_context.lint(&traits::AssocItemLint);
     trait_context.lint(&methods::MethodAttrLint);
+    trait_context.lint(&traits::ForwardLint);
 
     trait_context.expand(&traits::PollTraitExpand);
     trait_context.expand(&traits::ExtensionTraitExpand);
     trait_context.expand(&impls::ImplExtTraitExpand);
+    trait_context.expand(&impls::ImplTraitObjectExtExpand {
+        send: false,
+        sync: false,
+    });
+    trait_context.expand(&impls::ImplTraitObjectExtExpand {
+        send: true,
+        sync: false,
+    });
+    trait_context.expand(&impls::ImplTraitObjectExtExpand {
+        send: true,
+        sync: true,
+    });
+    for forward in input::Forward::ALL.iter() {
+        trait_context.expand(&impls::ImplForwardExpand(*forward));
+    }
+    trait_context.expand(&traits::DelegateMacroExpand);
+    trait_context.expand(&traits::NativeTraitExpand);
+    trait_context.expand(&impls::ImplNativeTraitExpand);
+    trait_context.expand(&traits::DynTraitExpand);
+    trait_context.expand(&impls::ImplDynTraitExpand);
+
+    collector.finish().into()
+}
+
+#[proc_macro_attribute]
+pub fn delegate(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item: proc_macro2::TokenStream = item.into();
+
+    let mut collector = Collector::new();
+
+    let mut args_context = Context::<input::DelegateArgs>::new_parse(&mut collector, args);
+    args_context.lint(&delegate::DelegateArgsLint);
+    args_context.expand(&delegate::DelegateCallExpand {
+        callback: "__delegate",
+        item: &item,
+    });
+
+    let mut output = item;
+    output.extend(collector.finish());
+    output.into()
+}
+
+#[proc_macro_attribute]
+pub fn dispatch(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item: proc_macro2::TokenStream = item.into();
+
+    let mut collector = Collector::new();
+
+    let mut args_context = Context::<input::DelegateArgs>::new_parse(&mut collector, args);
+    args_context.lint(&delegate::DelegateArgsLint);
+    args_context.lint(&delegate::DispatchArgsLint);
+    args_context.expand(&delegate::DelegateCallExpand {
+        callback: "__dispatch",
+        item: &item,
+    });
+
+    let mut output = item;
+    output.extend(collector.finish());
+    output.into()
+}
+
+/// called by the macro generated for `#[async_trait_ext(delegate)]` with the poll trait
+#[doc(hidden)]
+#[proc_macro]
+pub fn __delegate(input: TokenStream) -> TokenStream {
+    let mut collector = Collector::new();
+
+    let mut delegate_context = Context::<input::DelegateInput>::new_parse(&mut collector, input);
+    delegate_context.lint(&delegate::DelegateLint);
+    delegate_context.expand(&delegate::ImplDelegateExpand);
+
+    collector.finish().into()
+}
+
+/// called by the macro generated for `#[async_trait_ext(delegate)]` for `dispatch`
+#[doc(hidden)]
+#[proc_macro]
+pub fn __dispatch(input: TokenStream) -> TokenStream {
+    let mut collector = Collector::new();
+
+    let mut dispatch_context = Context::<input::DelegateInput>::new_parse(&mut collector, input);
+    dispatch_context.lint(&delegate::DispatchLint);
+    dispatch_context.expand(&delegate::ImplDispatchExpand);
 
     collector.finish().into()
 }
 
 fn ext_trait_name(input: &ItemTrait) -> Ident {
-    format_ident!("{}Ext", input.ident)
+    match input::TraitInput::ext(&input.attrs) {
+        Some(Ok(ident)) => ident,
+        _ => format_ident!("{}Ext", input.ident),
+    }
 }
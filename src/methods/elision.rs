@@ -0,0 +1,157 @@
+use crate::methods::{pinned_receiver, pointer_receiver};
+use proc_macro2::Span;
+use syn::{
+    parse_quote,
+    visit_mut::{visit_lifetime_mut, visit_receiver_mut, visit_type_reference_mut, VisitMut},
+    FnArg, GenericParam, Lifetime, LifetimeDef, ParenthesizedGenericArguments, Receiver,
+    ReturnType, TraitItem, TraitItemMethod, Type, TypeBareFn, TypeImplTrait, TypeReference,
+};
+
+/// gives every elided lifetime of a method an explicit name, following the rules of rustc
+pub fn elide_lifetimes(item: TraitItem) -> TraitItem {
+    match item {
+        TraitItem::Method(m) => TraitItem::Method(elide_method(&m)),
+        item => item,
+    }
+}
+
+pub fn elide_method(method: &TraitItemMethod) -> TraitItemMethod {
+    let mut item = method.clone();
+
+    let mut fresh = Vec::new();
+    let mut inputs = Elide {
+        lifetime: || {
+            let lifetime = Lifetime::new(&format!("'__elided_{}", fresh.len()), Span::call_site());
+            fresh.push(lifetime.clone());
+            lifetime
+        },
+        lifetimes: Vec::new(),
+    };
+    let mut self_lifetime = None;
+    for arg in item.sig.inputs.iter_mut() {
+        // `&Arc<Self>` receivers are cloned into the future, their lifetime isn't captured
+        if pointer_receiver(arg).is_some_and(|pointer| pointer.by_ref) {
+            continue;
+        }
+
+        inputs.visit_fn_arg_mut(arg);
+
+        if let FnArg::Receiver(Receiver {
+            reference: Some((_, lifetime)),
+            ..
+        }) = arg
+        {
+            self_lifetime = lifetime.clone();
+        } else if let Some(reference) = pinned_receiver(arg) {
+            self_lifetime = reference.lifetime.clone();
+        }
+    }
+
+    let output_lifetime = self_lifetime.or_else(|| match inputs.lifetimes.as_slice() {
+        [lifetime] => Some(lifetime.clone()),
+        _ => None,
+    });
+    if let (Some(lifetime), ReturnType::Type(_, ty)) = (output_lifetime, &mut item.sig.output) {
+        Elide {
+            lifetime: || lifetime.clone(),
+            lifetimes: Vec::new(),
+        }
+        .visit_type_mut(ty);
+    }
+
+    // lifetime parameters have to come before type and const parameters
+    let params = &mut item.sig.generics.params;
+    let idx = params
+        .iter()
+        .take_while(|param| matches!(param, GenericParam::Lifetime(_)))
+        .count();
+    for (i, lifetime) in fresh.into_iter().enumerate() {
+        params.insert(idx + i, GenericParam::Lifetime(LifetimeDef::new(lifetime)));
+    }
+
+    item
+}
+
+/// checks if the return type of a method contains elided lifetimes
+pub fn has_elided_output(method: &TraitItemMethod) -> bool {
+    match &method.sig.output {
+        ReturnType::Type(_, ty) => has_elided_lifetimes(ty),
+        ReturnType::Default => false,
+    }
+}
+
+/// checks if an `impl Trait` argument of a method contains elided lifetimes
+pub fn has_elided_impl_trait(method: &TraitItemMethod) -> bool {
+    struct FindElided(bool);
+
+    impl VisitMut for FindElided {
+        fn visit_type_impl_trait_mut(&mut self, i: &mut TypeImplTrait) {
+            self.0 |= has_elided_lifetimes(&Type::ImplTrait(i.clone()));
+        }
+    }
+
+    let mut find = FindElided(false);
+    for arg in method.sig.inputs.iter() {
+        if let FnArg::Typed(pt) = arg {
+            find.visit_type_mut(&mut pt.ty.as_ref().clone());
+        }
+    }
+    find.0
+}
+
+fn has_elided_lifetimes(ty: &Type) -> bool {
+    let mut elided = false;
+    Elide {
+        lifetime: || {
+            elided = true;
+            parse_quote!('_)
+        },
+        lifetimes: Vec::new(),
+    }
+    .visit_type_mut(&mut ty.clone());
+    elided
+}
+
+/// checks if the elided lifetimes in the return type of a method can't be inferred
+pub fn has_ambiguous_output(method: &TraitItemMethod) -> bool {
+    has_elided_output(&elide_method(method))
+}
+
+/// replaces elided lifetimes and collects all the other lifetimes
+struct Elide<F> {
+    lifetime: F,
+    lifetimes: Vec<Lifetime>,
+}
+
+impl<F: FnMut() -> Lifetime> VisitMut for Elide<F> {
+    fn visit_receiver_mut(&mut self, i: &mut Receiver) {
+        if let Some((_, lifetime)) = i.reference.as_mut() {
+            lifetime.get_or_insert_with(&mut self.lifetime);
+        }
+
+        visit_receiver_mut(self, i);
+    }
+
+    fn visit_type_reference_mut(&mut self, i: &mut TypeReference) {
+        i.lifetime.get_or_insert_with(&mut self.lifetime);
+
+        visit_type_reference_mut(self, i);
+    }
+
+    fn visit_lifetime_mut(&mut self, i: &mut Lifetime) {
+        if i.ident == "_" {
+            *i = (self.lifetime)();
+        }
+        if !self.lifetimes.contains(i) {
+            self.lifetimes.push(i.clone());
+        }
+
+        visit_lifetime_mut(self, i);
+    }
+
+    // function pointers and `Fn` traits have their own elision scope
+    fn visit_type_bare_fn_mut(&mut self, _: &mut TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments_mut(&mut self, _: &mut ParenthesizedGenericArguments) {
+    }
+}
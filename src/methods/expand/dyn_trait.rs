@@ -0,0 +1,439 @@
+use crate::methods::{
+    expand::{
+        forward_args,
+        native::{forward_path, NativeMethodExpand},
+    },
+    mentions_self_type, pointer_receiver,
+};
+use macro_compose::{Collector, Context, Expand};
+use quote::quote;
+use std::iter::FromIterator;
+use syn::{
+    parse_quote,
+    punctuated::Punctuated,
+    visit_mut::{visit_ident_mut, VisitMut},
+    Expr, FnArg, GenericParam, Ident, ItemTrait, Lifetime, LifetimeDef, Pat, ReturnType, Signature,
+    TraitBoundModifier, TraitItemMethod, Type, TypeParamBound, WherePredicate,
+};
+
+pub struct DynMethodExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for DynMethodExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        dyn_method(self.0, input, c).map(|method| method.item)
+    }
+}
+
+pub struct DynMethodImplExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for DynMethodImplExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        let DynMethod {
+            mut item,
+            args,
+            erased,
+        } = dyn_method(self.0, input, c)?;
+        let path = forward_path(self.0, input, c)?;
+
+        let turbofish = if args.is_empty() {
+            None
+        } else {
+            Some(quote!(::<#(#args),*>))
+        };
+
+        let owned_self =
+            matches!(input.sig.inputs.first(), Some(FnArg::Receiver(r)) if r.reference.is_none());
+        // like the extension futures, the boxed future owns a clone of an `&Arc<Self>` receiver
+        let cloned_self = item
+            .sig
+            .inputs
+            .first()
+            .and_then(pointer_receiver)
+            .is_some_and(|pointer| pointer.by_ref);
+        let args = forward_args(&mut item);
+        // `&mut dyn Trait` is passed as `&mut T`, so it has to be reborrowed mutably
+        for arg in item.sig.inputs.iter_mut() {
+            if let FnArg::Typed(pt) = arg {
+                if let Pat::Ident(pi) = &mut *pt.pat {
+                    if erased
+                        .iter()
+                        .any(|erased| erased.arg == pi.ident && matches!(erased.pass, Pass::Mut))
+                    {
+                        pi.mutability = Some(Default::default());
+                    }
+                }
+            }
+        }
+        let args = args.into_iter().map(|arg| -> Expr {
+            let erased = erased
+                .iter()
+                .find(|erased| matches!(&arg, Expr::Path(p) if p.path.is_ident(&erased.arg)));
+            match erased.map(|erased| &erased.pass) {
+                Some(Pass::Ref) => parse_quote!(&#arg),
+                Some(Pass::Mut) => parse_quote!(&mut #arg),
+                _ if matches!(&arg, Expr::Path(p) if p.path.is_ident("self")) => {
+                    if owned_self {
+                        parse_quote!(*self)
+                    } else if cloned_self {
+                        parse_quote!(&this)
+                    } else {
+                        arg
+                    }
+                }
+                _ => arg,
+            }
+        });
+        let clone_self = if cloned_self {
+            Some(quote!(let this = ::core::clone::Clone::clone(self);))
+        } else {
+            None
+        };
+
+        // the references to the trait objects are moved into the future before being borrowed
+        item.default = Some(parse_quote!(
+            {
+                #clone_self
+                ::std::boxed::Box::pin(async move { #path #turbofish( #(#args),* ).await })
+            }
+        ));
+        item.semi_token = None;
+
+        Some(item)
+    }
+}
+
+struct DynMethod {
+    item: TraitItemMethod,
+    args: Vec<Type>,
+    erased: Vec<Erased>,
+}
+
+enum Pass {
+    Value,
+    Ref,
+    Mut,
+}
+
+struct Erased {
+    arg: Ident,
+    pass: Pass,
+}
+
+fn dyn_method(item: &ItemTrait, input: &TraitItemMethod, c: &mut Collector) -> Option<DynMethod> {
+    let mut ctx = Context::new_by_ref(c, input);
+    let mut method = ctx.capture(&NativeMethodExpand(item))?;
+    method.sig.asyncness = None;
+
+    // methods that can't be dispatched on are left out of the vtable
+    let mut sized = false;
+    match method.sig.inputs.first() {
+        Some(FnArg::Receiver(r)) if r.reference.is_none() => {
+            method.sig.inputs[0] = parse_quote!(self: ::std::boxed::Box<Self>)
+        }
+        Some(arg) if pointer_receiver(arg).is_some_and(|pointer| pointer.by_ref) => sized = true,
+        _ => {}
+    }
+    sized |= method.sig.inputs.iter().skip(1).any(|arg| match arg {
+        FnArg::Typed(pt) => mentions_self_type(&pt.ty),
+        FnArg::Receiver(_) => false,
+    });
+    if let ReturnType::Type(_, ty) = &method.sig.output {
+        sized |= mentions_self_type(ty);
+    }
+
+    let mut args: Vec<Type> = method
+        .sig
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(tp) => {
+                let ident = &tp.ident;
+                Some(parse_quote!(#ident))
+            }
+            GenericParam::Const(cp) => {
+                let ident = &cp.ident;
+                Some(parse_quote!(#ident))
+            }
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let mut erased = Vec::new();
+    if !sized {
+        match erase_type_params(&method.sig) {
+            Some((sig, types)) => {
+                method.sig = sig;
+                for (arg, ident, pass, ty) in types {
+                    if let Some(slot) = args
+                        .iter_mut()
+                        .find(|arg| matches!(arg, Type::Path(tp) if tp.path.is_ident(&ident)))
+                    {
+                        *slot = ty;
+                    }
+                    erased.push(Erased { arg, pass });
+                }
+            }
+            None => sized = true,
+        }
+    }
+
+    // like boxed provided methods, the future captures all arguments and generic parameters
+    let lifetimes: Vec<Lifetime> = item
+        .generics
+        .lifetimes()
+        .chain(method.sig.generics.lifetimes())
+        .map(|ld| ld.lifetime.clone())
+        .collect();
+    let types: Vec<Ident> = item
+        .generics
+        .type_params()
+        .chain(method.sig.generics.type_params())
+        .map(|tp| tp.ident.clone())
+        .collect();
+    let idx = method.sig.generics.lifetimes().count();
+    method
+        .sig
+        .generics
+        .params
+        .insert(idx, parse_quote!('__boxed));
+    method
+        .sig
+        .generics
+        .lt_token
+        .get_or_insert_with(Default::default);
+    method
+        .sig
+        .generics
+        .gt_token
+        .get_or_insert_with(Default::default);
+
+    let predicates = &mut method.sig.generics.make_where_clause().predicates;
+    predicates.extend(
+        lifetimes
+            .iter()
+            .map(|lifetime| -> WherePredicate { parse_quote!(#lifetime: '__boxed) }),
+    );
+    predicates.extend(
+        types
+            .iter()
+            .map(|ident| -> WherePredicate { parse_quote!(#ident: '__boxed) }),
+    );
+    predicates.push(parse_quote!(Self: '__boxed));
+    if sized {
+        predicates.push(parse_quote!(Self: ::core::marker::Sized));
+    }
+
+    let output: Type = match &input.sig.output {
+        ReturnType::Default => parse_quote!(()),
+        ReturnType::Type(_, ty) => *ty.clone(),
+    };
+    method.sig.output = parse_quote!(
+        -> ::core::pin::Pin<::std::boxed::Box<dyn ::core::future::Future<Output = #output> + '__boxed>>
+    );
+
+    Some(DynMethod {
+        item: method,
+        args,
+        erased,
+    })
+}
+
+/// replaces type parameters only used as the type of a single argument with trait objects
+#[allow(clippy::type_complexity)]
+fn erase_type_params(sig: &Signature) -> Option<(Signature, Vec<(Ident, Ident, Pass, Type)>)> {
+    if sig.generics.const_params().next().is_some() {
+        return None;
+    }
+
+    let mut erased_sig = sig.clone();
+    let mut erased = Vec::new();
+    for param in sig.generics.type_params() {
+        let ident = &param.ident;
+
+        let mut uses = erased_sig
+            .inputs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, arg)| match arg {
+                FnArg::Typed(pt) => passed_as(&pt.ty, ident).map(|pass| (i, pass)),
+                FnArg::Receiver(_) => None,
+            });
+        let (idx, (pass, lifetime)) = match (uses.next(), uses.next()) {
+            (Some(arg), None) => arg,
+            _ => return None,
+        };
+
+        let mut bounds: Vec<TypeParamBound> = param.bounds.iter().cloned().collect();
+        erased_sig.generics.params = Punctuated::from_iter(
+            erased_sig
+                .generics
+                .params
+                .iter()
+                .filter(|p| !matches!(p, GenericParam::Type(tp) if tp.ident == *ident))
+                .cloned(),
+        );
+        if let Some(where_clause) = &mut erased_sig.generics.where_clause {
+            let mut predicates = Punctuated::new();
+            for predicate in where_clause.predicates.iter() {
+                match predicate {
+                    WherePredicate::Type(pt)
+                        if pt.lifetimes.is_none()
+                            && matches!(&pt.bounded_ty, Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident(ident)) =>
+                    {
+                        bounds.extend(pt.bounds.iter().cloned())
+                    }
+                    predicate => predicates.push(predicate.clone()),
+                }
+            }
+            where_clause.predicates = predicates;
+        }
+
+        let pt = match &mut erased_sig.inputs[idx] {
+            FnArg::Typed(pt) => pt,
+            FnArg::Receiver(_) => unreachable!(),
+        };
+        let arg = match &*pt.pat {
+            Pat::Ident(pi) => pi.ident.clone(),
+            _ => return None,
+        };
+        let trait_object = trait_object(&bounds, &pass, lifetime.is_none())?;
+        let ty: Type = match pass {
+            Pass::Value => parse_quote!(::std::boxed::Box<#trait_object>),
+            Pass::Ref => parse_quote!(& #lifetime #trait_object),
+            Pass::Mut => parse_quote!(& #lifetime mut #trait_object),
+        };
+        *pt.ty = ty.clone();
+
+        // the parameter can't be used anywhere else, and neither can a reference to it
+        if mentions(&erased_sig, ident)
+            || lifetime
+                .as_ref()
+                .is_some_and(|lifetime| lifetime_uses(&erased_sig, lifetime) > 1)
+        {
+            return None;
+        }
+
+        erased.push((arg, ident.clone(), pass, ty));
+    }
+
+    if erased_sig.generics.params.is_empty() {
+        erased_sig.generics.lt_token = None;
+        erased_sig.generics.gt_token = None;
+    }
+    Some((erased_sig, erased))
+}
+
+fn passed_as(ty: &Type, ident: &Ident) -> Option<(Pass, Option<Lifetime>)> {
+    let is_param =
+        |ty: &Type| matches!(ty, Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident(ident));
+    match ty {
+        ty if is_param(ty) => Some((Pass::Value, None)),
+        Type::Reference(r) if is_param(&r.elem) && r.mutability.is_some() => {
+            Some((Pass::Mut, r.lifetime.clone()))
+        }
+        Type::Reference(r) if is_param(&r.elem) => Some((Pass::Ref, r.lifetime.clone())),
+        _ => None,
+    }
+}
+
+/// the trait object standing in for the bounds of a type parameter
+fn trait_object(bounds: &[TypeParamBound], pass: &Pass, boxed_lifetime: bool) -> Option<Type> {
+    let mut main_trait = None;
+    let mut auto_traits = Vec::new();
+    let mut lifetime = None;
+    for bound in bounds {
+        let bound = match bound {
+            TypeParamBound::Trait(bound) => bound,
+            TypeParamBound::Lifetime(l) => {
+                lifetime = Some(l.clone());
+                continue;
+            }
+        };
+        if let TraitBoundModifier::Maybe(_) = bound.modifier {
+            continue;
+        }
+
+        let name = bound.path.segments.last()?.ident.to_string();
+        let (by_value, by_ref, by_mut) = match name.as_str() {
+            "Send" | "Sync" | "Unpin" => {
+                auto_traits.push(bound.clone());
+                continue;
+            }
+            "Fn" | "Debug" | "Display" => (true, true, true),
+            "FnMut"
+            | "Iterator"
+            | "DoubleEndedIterator"
+            | "ExactSizeIterator"
+            | "FusedIterator"
+            | "Read"
+            | "Write"
+            | "BufRead"
+            | "Seek"
+            | "Hasher" => (true, false, true),
+            "FnOnce" => (true, false, false),
+            _ => return None,
+        };
+        let forwarded = match pass {
+            Pass::Value => by_value,
+            Pass::Ref => by_ref,
+            Pass::Mut => by_mut,
+        };
+        if !forwarded || main_trait.is_some() {
+            return None;
+        }
+        main_trait = Some(bound.clone());
+    }
+
+    let main_trait = main_trait?;
+    let lifetime = lifetime.or_else(|| {
+        if matches!(pass, Pass::Value) && boxed_lifetime {
+            Some(parse_quote!('__boxed))
+        } else {
+            None
+        }
+    });
+    let lifetime = lifetime.into_iter();
+    Some(parse_quote!(dyn #main_trait #(+ #auto_traits)* #(+ #lifetime)*))
+}
+
+fn mentions(sig: &Signature, ident: &Ident) -> bool {
+    struct Mentions<'a>(&'a Ident, bool);
+
+    impl VisitMut for Mentions<'_> {
+        fn visit_ident_mut(&mut self, i: &mut Ident) {
+            self.1 |= i == self.0;
+            visit_ident_mut(self, i);
+        }
+    }
+
+    let mut visitor = Mentions(ident, false);
+    visitor.visit_signature_mut(&mut sig.clone());
+    visitor.1
+}
+
+fn lifetime_uses(sig: &Signature, lifetime: &Lifetime) -> usize {
+    struct LifetimeUses<'a>(&'a Lifetime, usize);
+
+    impl VisitMut for LifetimeUses<'_> {
+        fn visit_lifetime_mut(&mut self, i: &mut Lifetime) {
+            if i == self.0 {
+                self.1 += 1;
+            }
+        }
+
+        // the declaration isn't a use
+        fn visit_lifetime_def_mut(&mut self, i: &mut LifetimeDef) {
+            for bound in i.bounds.iter_mut() {
+                self.visit_lifetime_mut(bound);
+            }
+        }
+    }
+
+    let mut visitor = LifetimeUses(lifetime, 0);
+    visitor.visit_signature_mut(&mut sig.clone());
+    visitor.1
+}
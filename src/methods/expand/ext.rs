@@ -1,18 +1,28 @@
 use crate::{
     ext_trait_name,
     input::{MethodInput, TraitInput},
-    methods::{expand::GenericsExpand, future_type, CleanUpMutPatternsExpand},
+    methods::{
+        assoc_type_param, assoc_types, dyn_trait,
+        expand::{BoxedGenericsExpand, GenericsExpand, RenameSelf},
+        ext_method_ident, future_type, has_cancel_hook, is_boxed, is_fused, is_overridable,
+        is_owned, is_rpitit, is_state_machine, pinned_receiver, pointer_receiver,
+        provided_method_ident, referenced, state_type, takes_args, trait_bound,
+        CleanUpMutPatternsExpand,
+    },
 };
+use heck::SnakeCase;
 use macro_compose::{Collector, Context, Expand};
 use quote::format_ident;
+use std::iter::{once, FromIterator};
 use syn::{
     parse_quote,
+    punctuated::Punctuated,
     visit_mut::{
         visit_angle_bracketed_generic_arguments_mut, visit_block_mut, visit_ident_mut,
         visit_path_mut, VisitMut,
     },
-    AngleBracketedGenericArguments, Expr, FnArg, Ident, ItemTrait, Pat, Path, TraitItemMethod,
-    TypeGenerics,
+    AngleBracketedGenericArguments, Block, Expr, FnArg, GenericParam, Ident, ItemFn, ItemTrait,
+    Lifetime, Pat, Path, ReturnType, TraitItemMethod, TypeGenerics, TypeParam, WherePredicate,
 };
 
 pub struct ExtMethodExpand<'a>(pub &'a ItemTrait);
@@ -25,45 +35,169 @@ impl Expand<TraitItemMethod> for ExtMethodExpand<'_> {
         item.sig.asyncness.take()?;
         item.default.take();
 
-        let generics_expand = GenericsExpand {
-            item: self.0,
-            default_lifetime: parse_quote!('_),
-        };
+        let generics_expand = GenericsExpand(self.0);
         let mut ctx = Context::new_by_ref(c, input);
         let generics = ctx.capture(&generics_expand)?;
 
         let (_, ty_generics, _) = generics.split_for_impl();
 
-        let mut ctx = Context::new_by_ref(c, &ty_generics);
-        let args = ctx.capture(&RenameSelfExpand);
+        // dynamic traits may not need any generics
+        let args = if generics.params.is_empty() {
+            None
+        } else {
+            let mut ctx = Context::new_by_ref(c, &ty_generics);
+            ctx.capture(&RenameSelfExpand(self.0))
+        };
 
         let future_type = future_type(self.0, input);
         item.sig.output = parse_quote!(-> #future_type #args);
+        item.sig.ident = ext_method_ident(input);
+
+        let attrs = TraitInput::from(self.0.attrs.as_slice());
+        if is_boxed(input) {
+            let mut ctx = Context::new_by_ref(c, input);
+            let generics = ctx.capture(&BoxedGenericsExpand(self.0))?;
+            let (_, ty_generics, _) = generics.split_for_impl();
+            let mut ctx = Context::new_by_ref(c, &ty_generics);
+            let args = ctx.capture(&RenameSelfExpand(self.0));
+            item.sig.output = parse_quote!(-> #future_type #args);
+
+            // the boxed future captures all arguments and generic parameters
+            let lifetimes: Vec<Lifetime> = self
+                .0
+                .generics
+                .lifetimes()
+                .chain(item.sig.generics.lifetimes())
+                .map(|ld| ld.lifetime.clone())
+                .collect();
+            let types: Vec<Ident> = self
+                .0
+                .generics
+                .type_params()
+                .chain(item.sig.generics.type_params())
+                .map(|tp| tp.ident.clone())
+                .collect();
+            let idx = item.sig.generics.lifetimes().count();
+            item.sig.generics.params.insert(idx, parse_quote!('__boxed));
+
+            let predicates = &mut item.sig.generics.make_where_clause().predicates;
+            predicates.extend(
+                lifetimes
+                    .iter()
+                    .map(|lifetime| -> WherePredicate { parse_quote!(#lifetime: '__boxed) }),
+            );
+            predicates.extend(
+                types
+                    .iter()
+                    .map(|ident| -> WherePredicate { parse_quote!(#ident: '__boxed) }),
+            );
+            predicates.push(parse_quote!(Self: '__boxed));
+
+            // `Send` futures can only capture `&self` of `Sync` types and `&mut self` of `Send` types
+            if MethodInput::from(input.attrs.as_slice()).boxed.is_some() {
+                match input.sig.inputs.first() {
+                    Some(FnArg::Receiver(r)) if r.mutability.is_none() && r.reference.is_some() => {
+                        predicates.push(parse_quote!(Self: ::core::marker::Sync))
+                    }
+                    Some(FnArg::Receiver(_)) => {
+                        predicates.push(parse_quote!(Self: ::core::marker::Send))
+                    }
+                    _ => {}
+                }
+            }
+
+            // overrides aren't part of the vtable, trait objects would run the provided body
+            if attrs.dynamic.is_some() && is_overridable(input) {
+                predicates.push(parse_quote!(Self: ::core::marker::Sized));
+            }
+        } else if input.default.is_some() && is_rpitit(self.0) && !is_state_machine(input) {
+            let output = match &input.sig.output {
+                ReturnType::Default => parse_quote!(()),
+                ReturnType::Type(_, ty) => *ty.clone(),
+            };
+            item.sig.output = parse_quote!(-> impl ::core::future::Future<Output = #output>);
+
+            // methods returning `impl Trait` can't be called on trait objects
+            if attrs.dynamic.is_some() {
+                item.sig
+                    .generics
+                    .make_where_clause()
+                    .predicates
+                    .push(parse_quote!(Self: ::core::marker::Sized));
+            }
+        }
+
+        // the plain name is used by the `&mut self` variant
+        if input.sig.inputs.first().and_then(pinned_receiver).is_some() {
+            item.sig.ident = format_ident!("{}_pinned", input.sig.ident);
+        }
+
+        // the future of a dynamic trait stores a `Box<dyn Trait>`, `Rc<dyn Trait>` or `Arc<dyn Trait>`
+        if let Some(pointer) = input.sig.inputs.first().and_then(pointer_receiver) {
+            if attrs.dynamic.is_some() {
+                let predicates = &mut item.sig.generics.make_where_clause().predicates;
+                predicates.push(parse_quote!(Self: 'static));
+
+                // `&Arc<Self>` can't be dispatched on
+                if pointer.by_ref {
+                    predicates.push(parse_quote!(Self: ::core::marker::Sized));
+                }
+            }
+        }
+
+        MethodInput::remove(&mut item.attrs);
 
-        MethodInput::strip(&mut item.attrs);
+        Some(item)
+    }
+}
+
+/// the `&mut self` variant of a method with a `self: Pin<&mut Self>` receiver for `Unpin` types
+pub struct UnpinExtMethodExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for UnpinExtMethodExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        let lifetime = &input.sig.inputs.first().and_then(pinned_receiver)?.lifetime;
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
+
+        item.sig.ident = input.sig.ident.clone();
+        item.sig.inputs[0] = parse_quote!(& #lifetime mut self);
+        item.sig
+            .generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(Self: ::core::marker::Unpin));
 
         Some(item)
     }
 }
 
-struct RenameSelfExpand;
+struct RenameSelfExpand<'a>(&'a ItemTrait);
 
-impl Expand<TypeGenerics<'_>> for RenameSelfExpand {
+impl Expand<TypeGenerics<'_>> for RenameSelfExpand<'_> {
     type Output = AngleBracketedGenericArguments;
 
     fn expand(&self, input: &TypeGenerics<'_>, _: &mut Collector) -> Option<Self::Output> {
         let mut args = parse_quote!(#input);
-        visit_angle_bracketed_generic_arguments_mut(&mut RenameSelfVisit, &mut args);
+        visit_angle_bracketed_generic_arguments_mut(&mut RenameSelfVisit(self.0), &mut args);
         Some(args)
     }
 }
 
-struct RenameSelfVisit;
+struct RenameSelfVisit<'a>(&'a ItemTrait);
 
-impl VisitMut for RenameSelfVisit {
+impl VisitMut for RenameSelfVisit<'_> {
     fn visit_path_mut(&mut self, i: &mut Path) {
         if i.is_ident("__Self") {
             *i = parse_quote!(Self);
+        } else if let Some(ty) =
+            assoc_types(self.0).find(|ty| i.is_ident(&assoc_type_param(&ty.ident)))
+        {
+            let ident = &ty.ident;
+            *i = parse_quote!(Self::#ident);
         }
 
         visit_path_mut(self, i);
@@ -84,13 +218,14 @@ impl Expand<TraitItemMethod> for MethodExtImplExpand<'_> {
         let mut ctx = Context::new_by_ref(c, input);
         let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
 
+        let take_args = takes_args(input);
         let args = item
             .sig
             .inputs
             .iter_mut()
             .enumerate()
             .map(|(i, arg)| -> syn::Expr {
-                match arg {
+                let expr: Expr = match arg {
                     FnArg::Receiver(_) => parse_quote!(self),
                     FnArg::Typed(pt) => match &*pt.pat {
                         Pat::Ident(pi) => {
@@ -103,16 +238,29 @@ impl Expand<TraitItemMethod> for MethodExtImplExpand<'_> {
                             parse_quote!(#ident)
                         }
                     },
+                };
+
+                if take_args && is_owned(arg) {
+                    parse_quote!(::core::option::Option::Some(#expr))
+                } else {
+                    match pointer_receiver(arg) {
+                        Some(pointer) if pointer.by_ref => {
+                            let ty = pointer.ty;
+                            parse_quote!(<#ty as ::core::clone::Clone>::clone(#expr))
+                        }
+                        _ => expr,
+                    }
                 }
             });
 
-        let generics_expand = GenericsExpand {
-            item: self.0,
-            default_lifetime: parse_quote!('__default_lifetime),
-        };
+        let generics_expand = GenericsExpand(self.0);
         let mut ctx = Context::new_by_ref(c, input);
         let generics = ctx.capture(&generics_expand)?;
 
+        let args = args
+            .chain(state_type(input).map(|_| parse_quote!(::core::default::Default::default())));
+        let args = args.chain(has_cancel_hook(input).then(|| parse_quote!(false)));
+        let args = args.chain(is_fused(self.0).then(|| parse_quote!(false)));
         let args = args.chain(
             generics
                 .type_params()
@@ -135,6 +283,44 @@ impl Expand<TraitItemMethod> for MethodExtImplExpand<'_> {
     }
 }
 
+pub struct UnpinMethodExtImplExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for UnpinMethodExtImplExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        let lifetime = &input.sig.inputs.first().and_then(pinned_receiver)?.lifetime;
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut item = ctx.capture(&MethodExtImplExpand(self.0))?;
+
+        let pinned_ident = item.sig.ident.clone();
+        item.sig.ident = input.sig.ident.clone();
+        item.sig.inputs[0] = parse_quote!(& #lifetime mut self);
+        item.sig
+            .generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(Self: ::core::marker::Unpin));
+
+        // `MethodExtImplExpand` already replaced all patterns with identifiers
+        let args = item.sig.inputs.iter().skip(1).filter_map(|arg| match arg {
+            FnArg::Typed(pt) => match &*pt.pat {
+                Pat::Ident(pi) => Some(pi.ident.clone()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        });
+        item.default = Some(parse_quote!(
+            {
+                Self::#pinned_ident(::core::pin::Pin::new(self), #(#args),*)
+            }
+        ));
+
+        Some(item)
+    }
+}
+
 pub struct StaticProvidedMethodImplExpand<'a>(pub &'a ItemTrait);
 
 impl Expand<TraitItemMethod> for StaticProvidedMethodImplExpand<'_> {
@@ -142,25 +328,155 @@ impl Expand<TraitItemMethod> for StaticProvidedMethodImplExpand<'_> {
 
     fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
         let attrs = TraitInput::from(self.0.attrs.as_slice());
-        if attrs.dynamic.is_some() {
+        if attrs.dynamic.is_some() && !is_rpitit(self.0) && !is_boxed(input) {
             return None;
         }
         input.sig.asyncness.as_ref()?;
+        if is_state_machine(input) {
+            return None;
+        }
 
-        let default = input.default.clone().take()?;
+        let default = input.default.clone()?;
 
         let mut ctx = Context::new_by_ref(c, input);
         let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
 
-        item.default = Some(parse_quote!(
+        // the implementation of the trait may have replaced the provided one
+        if is_overridable(input) {
+            let args = forward_args(&mut item);
+            let ident = provided_method_ident(input);
+            item.default = Some(parse_quote!(
+                {
+                    Self::#ident( #(#args),* )
+                }
+            ));
+
+            let mut ctx = Context::new(c, item);
+            return ctx.capture(&CleanUpMutPatternsExpand);
+        }
+
+        if !is_rpitit(self.0) && !is_boxed(input) {
+            let future_type = future_type(self.0, input);
+            item.attrs
+                .push(parse_quote!(#[define_opaque(#future_type)]));
+        }
+        item.default = Some(provided_body(input, default));
+
+        Some(item)
+    }
+}
+
+pub struct OverridableMethodExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for OverridableMethodExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness.as_ref()?;
+        if !is_overridable(input) {
+            return None;
+        }
+        let default = input.default.clone()?;
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
+        item.sig.ident = provided_method_ident(input);
+        item.default = Some(provided_body(input, default));
+        item.semi_token = None;
+
+        // the body calls the extension trait, which is only implemented for sized types, the
+        // methods of dynamic traits already require them
+        let attrs = TraitInput::from(self.0.attrs.as_slice());
+        if attrs.dynamic.is_none() {
+            item.sig
+                .generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(Self: ::core::marker::Sized));
+        }
+
+        let comment = format!(
+            " the implementation of [`{}::{}`], which can be overridden",
+            ext_trait_name(self.0),
+            input.sig.ident
+        );
+        item.attrs = vec![parse_quote!(#[doc = #comment])];
+
+        Some(item)
+    }
+}
+
+fn provided_body(input: &TraitItemMethod, default: Block) -> Block {
+    if is_boxed(input) {
+        parse_quote!(
+            {
+                ::std::boxed::Box::pin(async move {
+                    #default
+                })
+            }
+        )
+    } else {
+        parse_quote!(
             {
                 async move {
                     #default
                 }
             }
-        ));
+        )
+    }
+}
 
-        Some(item)
+/// generic over the associated types, the blanket implementation can't define the opaque type
+pub struct DynamicProvidedFnExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for DynamicProvidedFnExpand<'_> {
+    type Output = ItemFn;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        let attrs = TraitInput::from(self.0.attrs.as_slice());
+        attrs.dynamic?;
+        input.sig.asyncness.as_ref()?;
+        if is_rpitit(self.0) || is_boxed(input) || is_state_machine(input) {
+            return None;
+        }
+
+        let mut default = input.default.clone()?;
+        visit_block_mut(&mut RenameselfExpand, &mut default);
+        visit_block_mut(&mut RenameSelf(self.0), &mut default);
+
+        // the extension trait is implemented for the trait object
+        let dyn_trait = referenced(dyn_trait(self.0, &self.0.ident));
+        let mut params: Vec<FnArg> = input
+            .sig
+            .inputs
+            .iter()
+            .map(|arg| {
+                if let FnArg::Receiver(r) = arg {
+                    let lifetime = r.reference.as_ref().and_then(|r| r.1.as_ref());
+                    let mutability = &r.mutability;
+                    parse_quote!(this: & #lifetime #mutability #dyn_trait)
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect();
+        for param in params.iter_mut() {
+            RenameSelf(self.0).visit_fn_arg_mut(param);
+        }
+
+        let generics_expand = GenericsExpand(self.0);
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&generics_expand)?;
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let future_type = future_type(self.0, input);
+        let fn_ident = provided_fn_ident(self.0, input);
+        Some(parse_quote!(
+            #[define_opaque(#future_type)]
+            fn #fn_ident #impl_generics( #(#params),* ) -> #future_type #ty_generics #where_clause {
+                async move #default
+            }
+        ))
     }
 }
 
@@ -173,63 +489,103 @@ impl Expand<TraitItemMethod> for DynamicProvidedMethodImplExpand<'_> {
         let attrs = TraitInput::from(self.0.attrs.as_slice());
         attrs.dynamic?;
         input.sig.asyncness.as_ref()?;
-
-        let mut default = input.default.clone().take()?;
-        visit_block_mut(&mut RenameselfExpand, &mut default);
+        input.default.as_ref()?;
+        if is_rpitit(self.0) || is_boxed(input) || is_state_machine(input) {
+            return None;
+        }
 
         let mut ctx = Context::new_by_ref(c, input);
         let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
 
-        let ext_ident = ext_trait_name(self.0);
-        let params = input.sig.inputs.iter().map(|arg| {
-            if let FnArg::Receiver(r) = arg {
-                let mutability = &r.mutability;
-                parse_quote!(this: & #mutability dyn #ext_ident)
-            } else {
-                arg.clone()
+        let args = forward_args(&mut item);
+        let fn_ident = provided_fn_ident(self.0, input);
+        item.default = Some(parse_quote!(
+            {
+                #fn_ident( #(#args),* )
             }
-        });
+        ));
 
-        let args = item
-            .sig
-            .inputs
-            .iter_mut()
-            .enumerate()
-            .map(|(i, arg)| -> Expr {
-                match arg {
-                    FnArg::Receiver(_) => parse_quote!(self),
-                    FnArg::Typed(pt) => match &mut *pt.pat {
-                        Pat::Ident(i) => {
-                            let ident = &mut i.ident;
-                            if ident == "self" {
-                                *ident = format_ident!("this", span = ident.span());
-                            }
-                            parse_quote!(#ident)
-                        }
-                        _ => {
-                            let ident = format_ident!("arg{}", i);
-                            pt.pat = parse_quote!(#ident);
-                            parse_quote!(#ident)
-                        }
-                    },
-                }
-            });
+        let mut ctx = Context::new(c, item);
+        ctx.capture(&CleanUpMutPatternsExpand)
+    }
+}
+
+/// the body is generic over `Self`, rustc can't prove futures holding `&mut dyn Trait` are `Send`
+pub struct TraitObjectProvidedMethodImplExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for TraitObjectProvidedMethodImplExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        let attrs = TraitInput::from(self.0.attrs.as_slice());
+        attrs.dynamic?;
+        input.sig.asyncness.as_ref()?;
+        MethodInput::from(input.attrs.as_slice()).boxed?;
+        let mut default = input.default.clone()?;
 
-        let generics_expand = GenericsExpand {
-            item: self.0,
-            default_lifetime: parse_quote!('__default_lifetime),
-        };
         let mut ctx = Context::new_by_ref(c, input);
-        let generics = ctx.capture(&generics_expand)?;
-        let (_, ty_generics, where_clause) = generics.split_for_impl();
+        let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
+
+        let mut sig = item.sig.clone();
+        sig.ident = provided_fn_ident(self.0, input);
+        if let Some(FnArg::Receiver(r)) = sig.inputs.first() {
+            let lifetime = r.reference.as_ref().and_then(|r| r.1.as_ref());
+            let mutability = &r.mutability;
+            sig.inputs[0] = parse_quote!(this: & #lifetime #mutability __Self);
+        }
+
+        let method_generics = sig.generics.clone();
+        let trait_generics = &self.0.generics;
+        let assoc_types = assoc_types(self.0).map(|ty| -> GenericParam {
+            let mut param = TypeParam::from(assoc_type_param(&ty.ident));
+            param.colon_token = ty.colon_token;
+            param.bounds = ty.bounds.clone();
+            GenericParam::Type(param)
+        });
+        let ext_trait = trait_bound(self.0, &ext_trait_name(self.0));
+        let self_type: GenericParam = parse_quote!(__Self: ?::core::marker::Sized + #ext_trait);
+        sig.generics.params = Punctuated::from_iter(
+            trait_generics
+                .lifetimes()
+                .chain(method_generics.lifetimes())
+                .cloned()
+                .map(GenericParam::Lifetime)
+                .chain(
+                    trait_generics
+                        .type_params()
+                        .chain(method_generics.type_params())
+                        .cloned()
+                        .map(GenericParam::Type),
+                )
+                .chain(assoc_types)
+                .chain(once(self_type))
+                .chain(
+                    trait_generics
+                        .const_params()
+                        .chain(method_generics.const_params())
+                        .cloned()
+                        .map(GenericParam::Const),
+                ),
+        );
+        let predicates = sig.generics.make_where_clause();
+        predicates.predicates.extend(
+            trait_generics
+                .where_clause
+                .iter()
+                .flat_map(|where_clause| where_clause.predicates.iter().cloned()),
+        );
+        RenameselfExpand.visit_signature_mut(&mut sig);
+        RenameSelf(self.0).visit_signature_mut(&mut sig);
+        visit_block_mut(&mut RenameselfExpand, &mut default);
+        visit_block_mut(&mut RenameSelf(self.0), &mut default);
+        let body = provided_body(input, default);
 
-        let output = &input.sig.output;
+        let args = forward_args(&mut item);
+        let fn_ident = &sig.ident;
         item.default = Some(parse_quote!(
             {
-                async fn fn_impl #ty_generics( #(#params),* ) #output #where_clause {
-                    #default
-                }
-                fn_impl( #(#args),* )
+                #sig #body
+                #fn_ident( #(#args),* )
             }
         ));
 
@@ -238,6 +594,38 @@ impl Expand<TraitItemMethod> for DynamicProvidedMethodImplExpand<'_> {
     }
 }
 
+pub fn forward_args(item: &mut TraitItemMethod) -> Vec<Expr> {
+    item.sig
+        .inputs
+        .iter_mut()
+        .enumerate()
+        .map(|(i, arg)| -> Expr {
+            match arg {
+                FnArg::Receiver(_) => parse_quote!(self),
+                FnArg::Typed(pt) => match &*pt.pat {
+                    Pat::Ident(pi) => {
+                        let ident = &pi.ident;
+                        parse_quote!(#ident)
+                    }
+                    _ => {
+                        let ident = format_ident!("arg{}", i);
+                        pt.pat = parse_quote!(#ident);
+                        parse_quote!(#ident)
+                    }
+                },
+            }
+        })
+        .collect()
+}
+
+fn provided_fn_ident(item: &ItemTrait, method: &TraitItemMethod) -> Ident {
+    format_ident!(
+        "__{}_{}",
+        item.ident.to_string().to_snake_case(),
+        method.sig.ident
+    )
+}
+
 struct RenameselfExpand;
 
 impl VisitMut for RenameselfExpand {
@@ -1,15 +1,21 @@
 use crate::{
-    input::TraitInput,
+    ext_trait_name,
+    input::{MethodInput, TraitInput},
     methods::{
-        expand::{GenericsExpand, NeedsDefaultLifetime},
-        future_type, RenameSelf,
+        boxed_output, dyn_trait,
+        expand::{BoxedGenericsExpand, GenericsExpand},
+        ext_method_ident, future_type, has_cancel_hook, is_boxed, is_fused, is_owned, is_rpitit,
+        is_self, is_state_machine, is_typed_receiver, pointer_receiver, referenced, state_type,
+        takes_args, RenameSelf,
     },
 };
 use macro_compose::{Collector, Context, Expand};
+use quote::quote;
 use syn::{
     parse_quote,
-    visit_mut::{visit_fn_arg_mut, visit_type_mut},
-    FnArg, ItemStruct, ItemTrait, ItemType, Lifetime, ReturnType, TraitItemMethod,
+    visit_mut::{visit_type_mut, visit_type_reference_mut, VisitMut},
+    FnArg, ItemStruct, ItemTrait, ItemType, Lifetime, ReturnType, TraitItemMethod, Type,
+    TypeParamBound, TypeReference,
 };
 
 pub struct FutureStructExpand<'a>(pub &'a ItemTrait);
@@ -24,46 +30,55 @@ impl Expand<TraitItemMethod> for FutureStructExpand<'_> {
         }
 
         let future_type = future_type(self.0, input);
-        let default_lifetime: Lifetime = parse_quote!('__default_lifetime);
-
         let attrs = TraitInput::from(self.0.attrs.as_slice());
 
-        let mut args = input.sig.inputs.clone();
-        let mut ndl = NeedsDefaultLifetime { res: false };
-        for arg in args.iter_mut() {
-            visit_fn_arg_mut(&mut ndl, arg);
-        }
-
-        let args = args.iter().map(|arg| match arg {
-            FnArg::Receiver(r) => {
-                let reference = r.reference.as_ref();
-                let lifetime = reference.map(|r| {
-                    r.1.as_ref()
-                        .cloned()
-                        .unwrap_or_else(|| default_lifetime.clone())
-                });
-                let reference = reference.map(|r| r.0);
-
-                let mutability = r.mutability;
-                if attrs.dynamic.is_some() {
-                    let (_, ty_generics, _) = self.0.generics.split_for_impl();
-                    let ident = &self.0.ident;
-                    parse_quote!(#reference #lifetime #mutability dyn #ident #ty_generics)
-                } else {
-                    parse_quote!(#reference #lifetime #mutability __Self)
+        let take_args = takes_args(input);
+        let args = input.sig.inputs.iter().map(|arg| {
+            let ty: Type = match arg {
+                FnArg::Receiver(r) => {
+                    let reference = r.reference.as_ref();
+                    let lifetime = reference.and_then(|r| r.1.as_ref());
+                    let mutability = reference.and(r.mutability);
+                    let reference = reference.map(|r| r.0);
+
+                    if attrs.dynamic.is_some() {
+                        let dyn_trait = dyn_trait(self.0, &self.0.ident);
+                        let dyn_trait = if reference.is_some() {
+                            referenced(dyn_trait)
+                        } else {
+                            dyn_trait
+                        };
+                        parse_quote!(#reference #lifetime #mutability #dyn_trait)
+                    } else {
+                        parse_quote!(#reference #lifetime #mutability __Self)
+                    }
                 }
+                FnArg::Typed(pt) => {
+                    let mut ty = match pointer_receiver(arg) {
+                        Some(pointer) => pointer.ty.clone(),
+                        None => *pt.ty.clone(),
+                    };
+                    if attrs.dynamic.is_some() && is_typed_receiver(arg) {
+                        ReplaceSelf(dyn_trait(self.0, &self.0.ident)).visit_type_mut(&mut ty);
+                    }
+                    ty
+                }
+            };
+
+            if take_args && is_owned(arg) {
+                parse_quote!(::core::option::Option<#ty>)
+            } else {
+                ty
             }
-            FnArg::Typed(pt) => *pt.ty.clone(),
         });
-        let args = args.map(|mut arg| {
-            visit_type_mut(&mut RenameSelf, &mut arg);
+        let args = args.chain(state_type(input)).map(|mut arg| {
+            RenameSelf(self.0).visit_type_mut(&mut arg);
             arg
         });
+        let args = args.chain(has_cancel_hook(input).then(|| parse_quote!(bool)));
+        let args = args.chain(is_fused(self.0).then(|| parse_quote!(bool)));
 
-        let generics_expand = GenericsExpand {
-            item: self.0,
-            default_lifetime: parse_quote!('__default_lifetime),
-        };
+        let generics_expand = GenericsExpand(self.0);
         let mut ctx = Context::new_by_ref(c, input);
         let generics = ctx.capture(&generics_expand)?;
         let (impl_generics, _, where_clause) = generics.split_for_impl();
@@ -78,8 +93,9 @@ impl Expand<TraitItemMethod> for FutureStructExpand<'_> {
         }));
 
         let comment = format!(
-            " the future returned by [`{}Ext::{}`]",
-            self.0.ident, input.sig.ident
+            " the future returned by [`{}::{}`]",
+            ext_trait_name(self.0),
+            ext_method_ident(input)
         );
 
         let vis = &self.0.vis;
@@ -90,6 +106,29 @@ impl Expand<TraitItemMethod> for FutureStructExpand<'_> {
     }
 }
 
+/// replaces `Self` with a concrete type
+struct ReplaceSelf(Type);
+
+impl VisitMut for ReplaceSelf {
+    fn visit_type_reference_mut(&mut self, i: &mut TypeReference) {
+        if is_self(&i.elem) {
+            *i.elem = referenced(self.0.clone());
+        } else {
+            visit_type_reference_mut(self, i);
+        }
+    }
+
+    fn visit_type_mut(&mut self, i: &mut Type) {
+        if let Type::Path(tp) = i {
+            if tp.qself.is_none() && tp.path.is_ident("Self") {
+                *i = self.0.clone();
+                return;
+            }
+        }
+        visit_type_mut(self, i);
+    }
+}
+
 pub struct FutureAliasExpand<'a>(pub &'a ItemTrait);
 
 impl Expand<TraitItemMethod> for FutureAliasExpand<'_> {
@@ -98,29 +137,57 @@ impl Expand<TraitItemMethod> for FutureAliasExpand<'_> {
     fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
         input.sig.asyncness?;
         input.default.as_ref()?;
+        if is_state_machine(input) {
+            return None;
+        }
 
-        let ty = match &input.sig.output {
+        let comment = format!(
+            " the future returned by [`{}::{}`]",
+            ext_trait_name(self.0),
+            ext_method_ident(input)
+        );
+        let vis = &self.0.vis;
+        let future_type = future_type(self.0, input);
+
+        if is_boxed(input) {
+            let mut ctx = Context::new_by_ref(c, input);
+            let generics = ctx.capture(&BoxedGenericsExpand(self.0))?;
+            let output = boxed_output(self.0, input);
+            let send = MethodInput::from(input.attrs.as_slice())
+                .boxed
+                .map(|_| quote!(+ ::core::marker::Send));
+
+            return Some(parse_quote!(
+                #[doc = #comment ]
+                #vis type #future_type #generics = ::core::pin::Pin<::std::boxed::Box<dyn ::core::future::Future<Output = #output> #send + '__boxed>>;
+            ));
+        }
+
+        if is_rpitit(self.0) {
+            return None;
+        }
+
+        let mut ty = match &input.sig.output {
             ReturnType::Default => parse_quote!(()),
             ReturnType::Type(_, ty) => *ty.clone(),
         };
+        RenameSelf(self.0).visit_type_mut(&mut ty);
 
-        let generics_expand = GenericsExpand {
-            item: self.0,
-            default_lifetime: parse_quote!('__default_lifetime),
-        };
+        let generics_expand = GenericsExpand(self.0);
         let mut ctx = Context::new_by_ref(c, input);
-        let generics = ctx.capture(&generics_expand)?;
+        let mut generics = ctx.capture(&generics_expand)?;
+
+        // the hidden type may capture references to `__Self` or trait objects using the type parameters
+        let lifetimes: Vec<Lifetime> = generics.lifetimes().map(|l| l.lifetime.clone()).collect();
+        for tp in generics.type_params_mut() {
+            tp.bounds
+                .extend(lifetimes.iter().cloned().map(TypeParamBound::Lifetime));
+        }
         let (impl_generics, _, where_clause) = generics.split_for_impl();
 
-        let comment = format!(
-            " the future returned by [`{}Ext::{}`]",
-            self.0.ident, input.sig.ident
-        );
-        let vis = &self.0.vis;
-        let future_type = future_type(self.0, input);
         Some(parse_quote!(
             #[doc = #comment ]
-            #vis type #future_type #impl_generics = impl ::core::future::Future<Output = #ty > #where_clause;
+            #vis type #future_type #impl_generics #where_clause = impl ::core::future::Future<Output = #ty >;
         ))
     }
 }
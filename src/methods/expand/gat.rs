@@ -0,0 +1,178 @@
+use crate::{
+    input::MethodInput,
+    methods::{gat_ident, is_gat_method},
+};
+use macro_compose::{Collector, Expand};
+use proc_macro2::Span;
+use syn::{
+    parse_quote,
+    visit_mut::{visit_type_mut, VisitMut},
+    FnArg, Generics, Ident, ItemTrait, Lifetime, ReturnType, TraitItemMethod, TraitItemType, Type,
+    WherePredicate,
+};
+
+pub struct GatTypeExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for GatTypeExpand<'_> {
+    type Output = TraitItemType;
+
+    fn expand(&self, input: &TraitItemMethod, _: &mut Collector) -> Option<Self::Output> {
+        if !is_gat_method(self.0, input) {
+            return None;
+        }
+
+        let mut generics = input.sig.generics.clone();
+        // the trait doesn't compile unless the bounds implied by the arguments are repeated
+        let bounds = implied_bounds(self.0, input);
+        if !bounds.is_empty() {
+            generics.make_where_clause().predicates.extend(bounds);
+        }
+        let where_clause = generics.where_clause.take();
+
+        let output: Type = match &input.sig.output {
+            ReturnType::Default => parse_quote!(()),
+            ReturnType::Type(_, ty) => *ty.clone(),
+        };
+
+        let comment = format!(" the future returned by [`Self::{}`]", input.sig.ident);
+        let ident = gat_ident(self.0, input);
+        Some(parse_quote!(
+            #[doc = #comment]
+            type #ident #generics: ::core::future::Future<Output = #output> #where_clause;
+        ))
+    }
+}
+
+pub struct GatMethodExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for GatMethodExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, _: &mut Collector) -> Option<Self::Output> {
+        if !is_gat_method(self.0, input) {
+            return None;
+        }
+
+        let mut item = input.clone();
+        item.sig.asyncness = None;
+
+        let ident = gat_ident(self.0, input);
+        let (_, ty_generics, _) = input.sig.generics.split_for_impl();
+        item.sig.output = parse_quote!(-> Self::#ident #ty_generics);
+
+        MethodInput::remove(&mut item.attrs);
+
+        Some(item)
+    }
+}
+
+/// the outlives bounds implied by the argument types of a method, e.g. `Self: 'a` for `&'a self`
+fn implied_bounds(item: &ItemTrait, method: &TraitItemMethod) -> Vec<WherePredicate> {
+    struct ImpliedBounds {
+        regions: Vec<Lifetime>,
+        lifetimes: Vec<Lifetime>,
+        types: Vec<Ident>,
+        stack: Vec<Lifetime>,
+        type_bounds: Vec<(Ident, Lifetime)>,
+        lifetime_bounds: Vec<(Lifetime, Lifetime)>,
+    }
+
+    impl VisitMut for ImpliedBounds {
+        fn visit_type_mut(&mut self, i: &mut Type) {
+            match i {
+                Type::Reference(reference) => {
+                    if let Some(lifetime) = &mut reference.lifetime {
+                        self.visit_lifetime_mut(lifetime);
+                    }
+
+                    let region = reference
+                        .lifetime
+                        .clone()
+                        .filter(|lifetime| self.regions.contains(lifetime));
+                    let pushed = region.is_some();
+                    self.stack.extend(region);
+                    self.visit_type_mut(&mut reference.elem);
+                    if pushed {
+                        self.stack.pop();
+                    }
+                }
+                // projections don't imply bounds on `Self` or their parameters
+                Type::Path(tp) if tp.qself.is_some() => {}
+                Type::Path(tp) if tp.path.get_ident().is_some() => {
+                    let ident = tp.path.get_ident().unwrap();
+                    if self.types.contains(ident) {
+                        for region in self.stack.iter() {
+                            let bound = (ident.clone(), region.clone());
+                            if !self.type_bounds.contains(&bound) {
+                                self.type_bounds.push(bound);
+                            }
+                        }
+                    }
+                }
+                _ => visit_type_mut(self, i),
+            }
+        }
+
+        fn visit_lifetime_mut(&mut self, i: &mut Lifetime) {
+            if !self.lifetimes.contains(i) {
+                return;
+            }
+            for region in self.stack.iter().filter(|region| *region != i) {
+                let bound = (i.clone(), region.clone());
+                if !self.lifetime_bounds.contains(&bound) {
+                    self.lifetime_bounds.push(bound);
+                }
+            }
+        }
+    }
+
+    let lifetimes = |generics: &Generics| {
+        generics
+            .lifetimes()
+            .map(|ld| ld.lifetime.clone())
+            .collect::<Vec<_>>()
+    };
+    let regions = lifetimes(&method.sig.generics);
+    let types = item
+        .generics
+        .type_params()
+        .chain(method.sig.generics.type_params())
+        .map(|tp| tp.ident.clone())
+        .chain(Some(Ident::new("Self", Span::call_site())))
+        .collect();
+    let mut visitor = ImpliedBounds {
+        lifetimes: lifetimes(&item.generics)
+            .into_iter()
+            .chain(regions.iter().cloned())
+            .collect(),
+        regions,
+        types,
+        stack: Vec::new(),
+        type_bounds: Vec::new(),
+        lifetime_bounds: Vec::new(),
+    };
+
+    for arg in method.sig.inputs.iter() {
+        let mut ty: Type = match arg {
+            FnArg::Receiver(r) => match &r.reference {
+                Some((_, lifetime)) => {
+                    let mutability = r.mutability;
+                    parse_quote!(& #lifetime #mutability Self)
+                }
+                None => parse_quote!(Self),
+            },
+            FnArg::Typed(pt) => *pt.ty.clone(),
+        };
+        visitor.visit_type_mut(&mut ty);
+    }
+
+    let type_bounds = visitor
+        .type_bounds
+        .into_iter()
+        .map(|(ident, region)| -> WherePredicate { parse_quote!(#ident: #region) });
+    let lifetime_bounds = visitor
+        .lifetime_bounds
+        .into_iter()
+        .map(|(lifetime, region)| -> WherePredicate { parse_quote!(#lifetime: #region) });
+    type_bounds.chain(lifetime_bounds).collect()
+}
@@ -1,14 +1,18 @@
 use crate::{
     input::TraitInput,
-    methods::{expand::GenericsExpand, future_type},
+    methods::{
+        expand::{GenericsExpand, RenameSelf},
+        future_type, has_cancel_hook, is_fused, is_owned, pinned_receiver, pointer_receiver,
+        poll_method_ident, state_type, takes_args,
+    },
 };
 use macro_compose::{Collector, Context, Expand};
 use proc_macro2::Span;
 use quote::format_ident;
 use std::iter::FromIterator;
 use syn::{
-    parse_quote, punctuated::Punctuated, Expr, GenericParam, Index, ItemImpl, ItemTrait, Member,
-    ReturnType, TraitItemMethod,
+    parse_quote, punctuated::Punctuated, visit_mut::VisitMut, Expr, FnArg, GenericParam, Ident,
+    Index, ItemImpl, ItemTrait, Member, ReturnType, Stmt, TraitItemMethod, Type,
 };
 
 pub struct ImplFutureExpand<'a>(pub &'a ItemTrait);
@@ -22,66 +26,227 @@ impl Expand<TraitItemMethod> for ImplFutureExpand<'_> {
             return None;
         }
 
-        let attrs = TraitInput::from(self.0.attrs.as_slice());
-
         let mut ctx = Context::new_by_ref(c, input);
-        let generics = ctx.capture(&GenericsExpand {
-            item: self.0,
-            default_lifetime: parse_quote!('__default_lifetime),
-        })?;
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
         let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-        let output = match &input.sig.output {
+        let mut output = match &input.sig.output {
             ReturnType::Default => parse_quote!(()),
             ReturnType::Type(_, ty) => *ty.clone(),
         };
+        RenameSelf(self.0).visit_type_mut(&mut output);
 
-        let args = input.sig.inputs.iter().enumerate().map(|(idx, _)| {
-            Member::Unnamed(Index {
-                index: idx as u32,
-                span: Span::call_site(),
-            })
-        });
-
-        let (_, trait_ty_generics, _) = self.0.generics.split_for_impl();
-        let trait_turbofish = trait_ty_generics.as_turbofish();
-
-        let mut generics = input.sig.generics.clone();
-        generics.params = Punctuated::from_iter(
-            input
-                .sig
-                .generics
-                .params
-                .iter()
-                .filter(|p| !matches!(p, GenericParam::Lifetime(_)))
-                .cloned(),
-        );
-        if generics.params.is_empty() {
-            generics.lt_token = None;
-            generics.gt_token = None;
-        }
-        let (_, method_ty_generics, _) = generics.split_for_impl();
-        let method_turbofish = method_ty_generics.as_turbofish();
+        let args = forwarded_args(input);
+        let path = trait_method_path(self.0, input, poll_method_ident(input));
 
-        let trait_ident = &self.0.ident;
-        let method_ident = format_ident!("poll_{}", input.sig.ident);
+        let future_type = future_type(self.0, input);
 
-        let path: Expr = if attrs.dynamic.is_some() {
-            parse_quote!(#trait_ident #trait_turbofish :: #method_ident #method_turbofish)
-        } else {
-            parse_quote!(<__Self as #trait_ident #trait_turbofish >:: #method_ident #method_turbofish)
-        };
+        let mut body: Vec<Stmt> = Vec::new();
+        if is_fused(self.0) {
+            let done = done_member(input);
+            let message = format!("`{}` polled after completion", future_type);
+            body.push(parse_quote!(if this.#done {
+                ::core::panic!(#message);
+            }));
+        }
+        body.push(parse_quote!(let res = #path ( #(#args,)* cx);));
+        if has_cancel_hook(input) {
+            let pending = pending_member(input);
+            body.push(parse_quote!(this.#pending = res.is_pending();));
+        }
+        if is_fused(self.0) {
+            let done = done_member(input);
+            body.push(parse_quote!(this.#done = res.is_ready();));
+        }
 
-        let future_type = future_type(self.0, input);
         Some(parse_quote!(
             impl #impl_generics ::core::future::Future for #future_type #ty_generics #where_clause {
                 type Output = #output;
 
                 fn poll(mut self: ::core::pin::Pin<&mut Self>, cx: &mut ::core::task::Context) -> ::core::task::Poll<Self::Output> {
                     let this = &mut *self;
-                    #path ( #(this. #args .into(),)* cx)
+                    #(#body)*
+                    res
+                }
+            }
+        ))
+    }
+}
+
+pub struct ImplUnpinExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for ImplUnpinExpand<'_> {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        if input.default.is_some() {
+            return None;
+        }
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        // the fields are never pinned, the poll method only gets regular references to them
+        let future_type = future_type(self.0, input);
+        Some(parse_quote!(
+            impl #impl_generics ::core::marker::Unpin for #future_type #ty_generics #where_clause {}
+        ))
+    }
+}
+
+/// implements `futures_core::future::FusedFuture` for the futures of `fused(futures_core)` traits
+pub struct ImplFusedFutureExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for ImplFusedFutureExpand<'_> {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        let attrs = TraitInput::from(self.0.attrs.as_slice());
+        if input.default.is_some() || attrs.fused.is_none() || attrs.futures_core.is_none() {
+            return None;
+        }
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let done = done_member(input);
+        let future_type = future_type(self.0, input);
+        Some(parse_quote!(
+            impl #impl_generics ::futures_core::future::FusedFuture for #future_type #ty_generics #where_clause {
+                fn is_terminated(&self) -> bool {
+                    self.#done
                 }
             }
         ))
     }
 }
+
+pub struct ImplDropExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for ImplDropExpand<'_> {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        if input.default.is_some() || !has_cancel_hook(input) {
+            return None;
+        }
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let args = forwarded_args(input);
+        let path = trait_method_path(self.0, input, format_ident!("cancel_{}", input.sig.ident));
+        let pending = pending_member(input);
+
+        let future_type = future_type(self.0, input);
+        Some(parse_quote!(
+            impl #impl_generics ::core::ops::Drop for #future_type #ty_generics #where_clause {
+                fn drop(&mut self) {
+                    let this = self;
+                    if this.#pending {
+                        #path ( #(#args),* )
+                    }
+                }
+            }
+        ))
+    }
+}
+
+fn member(index: usize) -> Member {
+    Member::Unnamed(Index {
+        index: index as u32,
+        span: Span::call_site(),
+    })
+}
+
+/// the field tracking whether the last poll returned `Poll::Pending`
+fn pending_member(input: &TraitItemMethod) -> Member {
+    member(input.sig.inputs.len() + state_type(input).iter().count())
+}
+
+/// the field tracking whether the future already returned `Poll::Ready`
+fn done_member(input: &TraitItemMethod) -> Member {
+    member(
+        input.sig.inputs.len() + state_type(input).iter().count() + has_cancel_hook(input) as usize,
+    )
+}
+
+/// checks if an argument is a `&mut` reference that has to be reborrowed
+fn is_mut_reference(arg: &FnArg) -> bool {
+    match arg {
+        FnArg::Receiver(r) => r.reference.is_some() && r.mutability.is_some(),
+        FnArg::Typed(pt) => matches!(&*pt.ty, Type::Reference(r) if r.mutability.is_some()),
+    }
+}
+
+/// the fields of a future `this` passed on to the poll method, excluding the context
+fn forwarded_args(input: &TraitItemMethod) -> impl Iterator<Item = Expr> + '_ {
+    let take_args = takes_args(input);
+    let args = input
+        .sig
+        .inputs
+        .iter()
+        .enumerate()
+        .map(move |(idx, arg)| -> Expr {
+            let member = member(idx);
+            if take_args && is_owned(arg) {
+                parse_quote!(&mut this.#member)
+            } else if pinned_receiver(arg).is_some() {
+                parse_quote!(this.#member.as_mut())
+            } else if let Some(pointer) = pointer_receiver(arg) {
+                if pointer.unique {
+                    parse_quote!(&mut *this.#member)
+                } else {
+                    parse_quote!(&*this.#member)
+                }
+            } else if is_mut_reference(arg) {
+                parse_quote!(&mut *this.#member)
+            } else {
+                // `.into()` would pick up `Into` bounds of generic arguments
+                parse_quote!(this.#member)
+            }
+        });
+    let state = state_type(input).map(|_| -> Expr {
+        let member = member(input.sig.inputs.len());
+        parse_quote!(&mut this.#member)
+    });
+    args.chain(state)
+}
+
+/// the path to a method of the trait that takes the same generics as the async method
+pub fn trait_method_path(item: &ItemTrait, input: &TraitItemMethod, method_ident: Ident) -> Expr {
+    let attrs = TraitInput::from(item.attrs.as_slice());
+
+    let (_, trait_ty_generics, _) = item.generics.split_for_impl();
+    let trait_turbofish = trait_ty_generics.as_turbofish();
+
+    let mut generics = input.sig.generics.clone();
+    generics.params = Punctuated::from_iter(
+        input
+            .sig
+            .generics
+            .params
+            .iter()
+            .filter(|p| !matches!(p, GenericParam::Lifetime(_)))
+            .cloned(),
+    );
+    if generics.params.is_empty() {
+        generics.lt_token = None;
+        generics.gt_token = None;
+    }
+    let (_, method_ty_generics, _) = generics.split_for_impl();
+    let method_turbofish = method_ty_generics.as_turbofish();
+
+    let trait_ident = &item.ident;
+    if attrs.dynamic.is_some() {
+        parse_quote!(#trait_ident #trait_turbofish :: #method_ident #method_turbofish)
+    } else {
+        parse_quote!(<__Self as #trait_ident #trait_turbofish >:: #method_ident #method_turbofish)
+    }
+}
@@ -1,119 +1,100 @@
+mod dyn_trait;
 mod ext;
 mod future_type;
+mod gat;
 mod impls;
+mod native;
 mod poll;
+mod state_machine;
 
+pub use dyn_trait::{DynMethodExpand, DynMethodImplExpand};
 pub use ext::{
-    DynamicProvidedMethodImplExpand, ExtMethodExpand, MethodExtImplExpand,
-    StaticProvidedMethodImplExpand,
+    forward_args, DynamicProvidedFnExpand, DynamicProvidedMethodImplExpand, ExtMethodExpand,
+    MethodExtImplExpand, OverridableMethodExpand, StaticProvidedMethodImplExpand,
+    TraitObjectProvidedMethodImplExpand, UnpinExtMethodExpand, UnpinMethodExtImplExpand,
 };
 pub use future_type::{FutureAliasExpand, FutureStructExpand};
-pub use impls::ImplFutureExpand;
-pub use poll::PollMethodExpand;
+pub use gat::{GatMethodExpand, GatTypeExpand};
+pub use impls::{
+    trait_method_path, ImplDropExpand, ImplFusedFutureExpand, ImplFutureExpand, ImplUnpinExpand,
+};
+pub use native::{turbofish, NativeMethodExpand, NativeMethodImplExpand};
+pub use poll::{CancelMethodExpand, PollMethodExpand};
+pub use state_machine::{
+    ImplStateMachineFutureExpand, ImplStateMachineUnpinExpand, StateMachineMethodImplExpand,
+    StateMachineStructExpand,
+};
 
-use crate::input::TraitInput;
-use macro_compose::{Collector, Expand};
+use crate::{
+    input::TraitInput,
+    methods::{assoc_type_param, assoc_types, self_assoc_type},
+};
+use macro_compose::{Collector, Context, Expand};
 use proc_macro2::Span;
-use std::iter::FromIterator;
+use std::iter::{once, FromIterator};
 use syn::{
     parse_quote,
     punctuated::Punctuated,
-    visit_mut::{
-        visit_fn_arg_mut, visit_generics_mut, visit_ident_mut, visit_receiver_mut,
-        visit_type_reference_mut, VisitMut,
-    },
-    FnArg, GenericParam, Generics, Ident, ItemTrait, LifetimeDef, Pat, Receiver, Token,
-    TraitItemMethod, TypeReference, WhereClause,
+    visit_mut::{visit_generics_mut, visit_ident_mut, visit_type_mut, VisitMut},
+    FnArg, GenericParam, Generics, Ident, ItemTrait, Lifetime, LifetimeDef, Pat, ReturnType, Token,
+    TraitItemMethod, Type, TypeParam, WhereClause,
 };
 
-struct NeedsDefaultLifetime {
-    res: bool,
-}
-
-impl VisitMut for NeedsDefaultLifetime {
-    fn visit_receiver_mut(&mut self, i: &mut Receiver) {
-        if let Some((_, lt)) = i.reference.as_mut() {
-            lt.get_or_insert_with(|| {
-                self.res = true;
-                parse_quote!('__default_lifetime)
-            });
-        }
-
-        visit_receiver_mut(self, i);
-    }
-
-    fn visit_type_reference_mut(&mut self, i: &mut TypeReference) {
-        i.lifetime.get_or_insert_with(|| {
-            self.res = true;
-            parse_quote!('__default_lifetime)
-        });
-
-        visit_type_reference_mut(self, i);
-    }
-}
-
-struct GenericsExpand<'a> {
-    item: &'a ItemTrait,
-    default_lifetime: LifetimeDef,
-}
+struct GenericsExpand<'a>(&'a ItemTrait);
 
 impl Expand<TraitItemMethod> for GenericsExpand<'_> {
     type Output = Generics;
 
     fn expand(&self, input: &TraitItemMethod, _: &mut Collector) -> Option<Self::Output> {
-        let attrs = TraitInput::from(self.item.attrs.as_slice());
-
-        let mut args = input.sig.inputs.clone();
-        let mut ndl = NeedsDefaultLifetime { res: false };
-        for arg in args.iter_mut() {
-            visit_fn_arg_mut(&mut ndl, arg);
-        }
-
-        let default_lifetime_def = if ndl.res {
-            Some(self.default_lifetime.clone())
-        } else {
-            None
-        };
+        let attrs = TraitInput::from(self.0.attrs.as_slice());
 
         let lifetimes = self
-            .item
+            .0
             .generics
             .lifetimes()
             .chain(input.sig.generics.lifetimes())
-            .cloned()
-            .chain(default_lifetime_def);
+            .cloned();
 
         let self_type: Option<syn::TypeParam> = if attrs.dynamic.is_none() {
-            let ident = &self.item.ident;
-            let (_, ty_generics, _) = self.item.generics.split_for_impl();
+            let ident = &self.0.ident;
+            let (_, ty_generics, _) = self.0.generics.split_for_impl();
             Some(parse_quote!(__Self: #ident #ty_generics))
         } else {
             None
         };
+        // trait objects of dynamic traits have to name their associated types
+        let assoc_types = assoc_types(self.0)
+            .filter(|_| attrs.dynamic.is_some())
+            .map(|ty| {
+                let mut param = syn::TypeParam::from(assoc_type_param(&ty.ident));
+                param.colon_token = ty.colon_token;
+                param.bounds = ty.bounds.clone();
+                param
+            });
         let types = self
-            .item
+            .0
             .generics
             .type_params()
             .chain(input.sig.generics.type_params())
             .cloned()
-            .chain(self_type);
+            .chain(self_type)
+            .chain(assoc_types);
 
         let consts = self
-            .item
+            .0
             .generics
             .const_params()
             .chain(input.sig.generics.const_params())
             .cloned();
 
         let where_clauses = self
-            .item
+            .0
             .generics
             .where_clause
             .as_ref()
             .into_iter()
             .chain(input.sig.generics.where_clause.as_ref())
-            .map(|c| c.predicates.iter())
-            .flatten()
+            .flat_map(|c| c.predicates.iter())
             .cloned();
 
         let where_clause = Punctuated::from_iter(where_clauses);
@@ -140,15 +121,116 @@ impl Expand<TraitItemMethod> for GenericsExpand<'_> {
             where_clause,
         };
 
-        visit_generics_mut(&mut RenameSelf, &mut generics);
+        visit_generics_mut(&mut RenameSelf(self.0), &mut generics);
 
         Some(generics)
     }
 }
 
-pub struct RenameSelf;
+/// the generics of the type alias of a boxed future: `'__boxed` followed by the parameters used by
+/// the output
+struct BoxedGenericsExpand<'a>(&'a ItemTrait);
+
+impl Expand<TraitItemMethod> for BoxedGenericsExpand<'_> {
+    type Output = Generics;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
+
+        let mut used = UsedParams::default();
+        used.visit_type_mut(&mut boxed_output(self.0, input));
+
+        // type aliases can't have unused type parameters and don't check bounds
+        let params = generics.params.into_iter().filter_map(|param| match param {
+            GenericParam::Lifetime(ld) if used.lifetimes.contains(&ld.lifetime) => {
+                Some(GenericParam::Lifetime(LifetimeDef::new(ld.lifetime)))
+            }
+            GenericParam::Type(tp) if used.idents.contains(&tp.ident) => {
+                Some(GenericParam::Type(TypeParam::from(tp.ident)))
+            }
+            GenericParam::Const(mut cp) if used.idents.contains(&cp.ident) => {
+                cp.eq_token = None;
+                cp.default = None;
+                Some(GenericParam::Const(cp))
+            }
+            _ => None,
+        });
+        let boxed = GenericParam::Lifetime(parse_quote!('__boxed));
+
+        Some(Generics {
+            lt_token: Some(Token![<](Span::call_site())),
+            params: Punctuated::from_iter(once(boxed).chain(params)),
+            gt_token: Some(Token![>](Span::call_site())),
+            where_clause: None,
+        })
+    }
+}
+
+/// the output of a boxed future, with associated types of static traits qualified because the
+/// type alias doesn't know that `__Self` implements the trait
+pub fn boxed_output(item: &ItemTrait, input: &TraitItemMethod) -> Type {
+    struct QualifyAssocTypes<'a>(&'a ItemTrait);
+
+    impl VisitMut for QualifyAssocTypes<'_> {
+        fn visit_type_mut(&mut self, i: &mut Type) {
+            if let Type::Path(tp) = i {
+                if let Some(ident) = self_assoc_type(self.0, tp) {
+                    let trait_ident = &self.0.ident;
+                    let (_, ty_generics, _) = self.0.generics.split_for_impl();
+                    *i = parse_quote!(<Self as #trait_ident #ty_generics>::#ident);
+                    return;
+                }
+            }
+
+            visit_type_mut(self, i);
+        }
+    }
+
+    let mut ty = match &input.sig.output {
+        ReturnType::Default => parse_quote!(()),
+        ReturnType::Type(_, ty) => *ty.clone(),
+    };
+    QualifyAssocTypes(item).visit_type_mut(&mut ty);
+    RenameSelf(item).visit_type_mut(&mut ty);
+    ty
+}
+
+#[derive(Default)]
+struct UsedParams {
+    idents: Vec<Ident>,
+    lifetimes: Vec<Lifetime>,
+}
+
+impl VisitMut for UsedParams {
+    fn visit_ident_mut(&mut self, i: &mut Ident) {
+        self.idents.push(i.clone());
+    }
+
+    fn visit_lifetime_mut(&mut self, i: &mut Lifetime) {
+        self.lifetimes.push(i.clone());
+    }
+}
+
+/// renames `Self` to `__Self`, and `Self::Item` to `__Item` in dynamic traits
+pub struct RenameSelf<'a>(pub &'a ItemTrait);
+
+impl VisitMut for RenameSelf<'_> {
+    fn visit_type_mut(&mut self, i: &mut Type) {
+        let attrs = TraitInput::from(self.0.attrs.as_slice());
+        if attrs.dynamic.is_some() {
+            if let Type::Path(tp) = i {
+                if let Some(ident) = self_assoc_type(self.0, tp) {
+                    let ident = assoc_type_param(ident);
+                    *i = parse_quote!(#ident);
+                    return;
+                }
+            }
+        }
+
+        visit_type_mut(self, i);
+    }
 
-impl VisitMut for RenameSelf {
     fn visit_ident_mut(&mut self, i: &mut Ident) {
         if i == "Self" {
             *i = Ident::new("__Self", i.span());
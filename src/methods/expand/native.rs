@@ -0,0 +1,150 @@
+use crate::{
+    ext_trait_name,
+    methods::{
+        expand::forward_args, is_boxed, is_gat_method, pinned_receiver, CleanUpMutPatternsExpand,
+        ExtMethodExpand,
+    },
+};
+use macro_compose::{Collector, Context, Expand};
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::iter::FromIterator;
+use syn::{
+    parse_quote, punctuated::Punctuated, Expr, ExprPath, GenericParam, Generics, ItemTrait,
+    Lifetime, ReturnType, TraitItemMethod, Type, TypeParamBound, WherePredicate,
+};
+
+pub struct NativeMethodExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for NativeMethodExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
+        item.sig.asyncness = input.sig.asyncness;
+        item.sig.output = input.sig.output.clone();
+        item.default = None;
+        item.semi_token = Some(Default::default());
+
+        // native traits take the pinned receiver like the declaration
+        if input.sig.inputs.first().and_then(pinned_receiver).is_some() {
+            item.sig.ident = input.sig.ident.clone();
+        }
+
+        // the lifetime of a boxed future is picked by the blanket implementation
+        let boxed: Lifetime = parse_quote!('__boxed);
+        let generics = &mut item.sig.generics;
+        generics.params = Punctuated::from_iter(
+            generics
+                .params
+                .iter()
+                .filter(
+                    |param| !matches!(param, GenericParam::Lifetime(ld) if ld.lifetime == boxed),
+                )
+                .cloned(),
+        );
+        if let Some(where_clause) = &mut generics.where_clause {
+            where_clause.predicates = Punctuated::from_iter(
+                where_clause
+                    .predicates
+                    .iter()
+                    .filter(|predicate| !outlives(predicate, &boxed))
+                    .cloned(),
+            );
+            if where_clause.predicates.is_empty() {
+                generics.where_clause = None;
+            }
+        }
+
+        // functions without bodies can't have patterns
+        forward_args(&mut item);
+        let mut ctx = Context::new(c, item);
+        ctx.capture(&CleanUpMutPatternsExpand)
+    }
+}
+
+pub struct NativeMethodImplExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for NativeMethodImplExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut item = ctx.capture(&NativeMethodExpand(self.0))?;
+        let path = forward_path(self.0, input, c)?;
+        let turbofish = turbofish(&item.sig.generics);
+        let args = forward_args(&mut item);
+
+        let output: Type = match &input.sig.output {
+            ReturnType::Default => parse_quote!(()),
+            ReturnType::Type(_, ty) => *ty.clone(),
+        };
+        item.sig.asyncness = None;
+        item.sig.output = parse_quote!(-> impl ::core::future::Future<Output = #output>);
+        let call: Expr = parse_quote!(
+            #path #turbofish( #(#args),* )
+        );
+        // the future can't name the lifetime of the box, it has to be picked inside
+        item.default = Some(if is_boxed(input) {
+            parse_quote!(
+                {
+                    async move { #call.await }
+                }
+            )
+        } else {
+            parse_quote!(
+                {
+                    #call
+                }
+            )
+        });
+        item.semi_token = None;
+
+        Some(item)
+    }
+}
+
+pub fn forward_path(
+    item: &ItemTrait,
+    input: &TraitItemMethod,
+    c: &mut Collector,
+) -> Option<ExprPath> {
+    let mut ctx = Context::new_by_ref(c, input);
+    let ident = ctx.capture(&ExtMethodExpand(item))?.sig.ident;
+
+    let trait_ident = if is_gat_method(item, input) {
+        item.ident.clone()
+    } else {
+        ext_trait_name(item)
+    };
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    Some(parse_quote!(<Self as #trait_ident #ty_generics>::#ident))
+}
+
+/// the turbofish of the type and const parameters of a method, lifetimes are inferred
+pub fn turbofish(generics: &Generics) -> Option<TokenStream> {
+    let types = generics.params.iter().filter_map(|param| match param {
+        GenericParam::Type(tp) => Some(&tp.ident),
+        GenericParam::Const(cp) => Some(&cp.ident),
+        GenericParam::Lifetime(_) => None,
+    });
+    if generics.type_params().next().is_some() || generics.const_params().next().is_some() {
+        Some(quote!(::<#(#types),*>))
+    } else {
+        None
+    }
+}
+
+pub fn outlives(predicate: &WherePredicate, lifetime: &Lifetime) -> bool {
+    match predicate {
+        WherePredicate::Lifetime(pl) => pl.bounds.iter().any(|bound| bound == lifetime),
+        WherePredicate::Type(pt) => pt
+            .bounds
+            .iter()
+            .any(|bound| matches!(bound, TypeParamBound::Lifetime(l) if l == lifetime)),
+        WherePredicate::Eq(_) => false,
+    }
+}
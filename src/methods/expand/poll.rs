@@ -1,11 +1,17 @@
-use crate::input::MethodInput;
-use macro_compose::{Collector, Expand};
+use crate::{
+    input::MethodInput,
+    methods::{
+        has_cancel_hook, is_owned, mentions_self, mentions_self_type, pointer_receiver,
+        poll_method_ident, state_type, takes_args,
+    },
+};
+use macro_compose::{Collector, Context, Expand};
 use quote::format_ident;
-use syn::{self, parse_quote, ItemTrait, ReturnType, TraitItemMethod};
+use syn::{self, parse_quote, FnArg, ReturnType, TraitItemMethod};
 
-pub struct PollMethodExpand<'a>(pub &'a ItemTrait);
+pub struct PollMethodExpand;
 
-impl Expand<TraitItemMethod> for PollMethodExpand<'_> {
+impl Expand<TraitItemMethod> for PollMethodExpand {
     type Output = TraitItemMethod;
 
     fn expand(&self, input: &TraitItemMethod, _: &mut Collector) -> Option<Self::Output> {
@@ -15,7 +21,45 @@ impl Expand<TraitItemMethod> for PollMethodExpand<'_> {
 
         if item.sig.asyncness.take().is_some() {
             if attrs.provided.is_none() {
-                item.sig.ident = format_ident!("poll_{}", input.sig.ident);
+                item.sig.ident = poll_method_ident(input);
+
+                if takes_args(input) {
+                    let mut needs_sized = false;
+                    for arg in item.sig.inputs.iter_mut().filter(|arg| is_owned(arg)) {
+                        match arg {
+                            FnArg::Receiver(_) => {
+                                needs_sized = true;
+                                *arg = parse_quote!(this: &mut ::core::option::Option<Self>);
+                            }
+                            FnArg::Typed(pt) => {
+                                needs_sized |= mentions_self(&pt.ty);
+                                let ty = &pt.ty;
+                                pt.ty = parse_quote!(&mut ::core::option::Option<#ty>);
+                            }
+                        }
+                    }
+
+                    if needs_sized {
+                        item.sig
+                            .generics
+                            .make_where_clause()
+                            .predicates
+                            .push(parse_quote!(Self: ::core::marker::Sized));
+                    }
+                }
+
+                // the future owns the pointer, the poll method only borrows the value
+                if let Some(receiver) = item.sig.inputs.first_mut() {
+                    match pointer_receiver(receiver) {
+                        Some(pointer) if pointer.unique => *receiver = parse_quote!(&mut self),
+                        Some(_) => *receiver = parse_quote!(&self),
+                        None => {}
+                    }
+                }
+
+                if let Some(state) = state_type(input) {
+                    item.sig.inputs.push(parse_quote!(state: &mut #state));
+                }
 
                 item.sig
                     .inputs
@@ -26,13 +70,56 @@ impl Expand<TraitItemMethod> for PollMethodExpand<'_> {
                     ReturnType::Type(_, ty) => *ty,
                 };
 
+                // `Poll<Self>` is only well-formed for sized types
+                if mentions_self_type(&output) {
+                    item.sig
+                        .generics
+                        .make_where_clause()
+                        .predicates
+                        .push(parse_quote!(Self: ::core::marker::Sized));
+                }
+
                 item.sig.output = parse_quote!(-> ::core::task::Poll< #output >);
             } else {
                 return None;
             }
         }
 
-        MethodInput::strip(&mut item.attrs);
+        MethodInput::remove(&mut item.attrs);
+
+        Some(item)
+    }
+}
+
+pub struct CancelMethodExpand;
+
+impl Expand<TraitItemMethod> for CancelMethodExpand {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        if input.default.is_some() || !has_cancel_hook(input) {
+            return None;
+        }
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut item = ctx.capture(&PollMethodExpand)?;
+
+        // the same arguments as the poll method, but without the context
+        item.sig.ident = format_ident!("cancel_{}", input.sig.ident);
+        item.sig.inputs.pop();
+        item.sig.output = ReturnType::Default;
+        item.default = Some(parse_quote!({}));
+        item.semi_token = None;
+
+        let comment = format!(
+            " called when a pending future returned by `{}` is dropped",
+            input.sig.ident
+        );
+        item.attrs = vec![
+            parse_quote!(#[doc = #comment]),
+            parse_quote!(#[allow(unused_variables)]),
+        ];
 
         Some(item)
     }
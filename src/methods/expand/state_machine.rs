@@ -0,0 +1,188 @@
+use crate::{
+    ext_trait_name,
+    input::TraitInput,
+    methods::{
+        dyn_trait,
+        expand::{GenericsExpand, RenameSelf},
+        ext_method_ident, future_type, is_state_machine, lower_state_machine, referenced,
+        CleanUpMutPatternsExpand, ExtMethodExpand,
+    },
+};
+use macro_compose::{Collector, Context, Expand};
+use quote::quote;
+use syn::{
+    parse_quote, visit_mut::VisitMut, FnArg, ItemImpl, ItemStruct, ItemTrait, ReturnType,
+    TraitItemMethod, Type,
+};
+
+pub struct StateMachineStructExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for StateMachineStructExpand<'_> {
+    type Output = ItemStruct;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        if !is_state_machine(input) {
+            return None;
+        }
+        let machine = lower_state_machine(self.0, input).ok()?;
+
+        let receiver: Type = match input.sig.inputs.first()? {
+            FnArg::Receiver(r) => {
+                let lifetime = r.reference.as_ref().and_then(|r| r.1.as_ref());
+                let mutability = r.mutability;
+                if TraitInput::from(self.0.attrs.as_slice()).dynamic.is_some() {
+                    let dyn_trait = referenced(dyn_trait(self.0, &self.0.ident));
+                    parse_quote!(& #lifetime #mutability #dyn_trait)
+                } else {
+                    parse_quote!(& #lifetime #mutability __Self)
+                }
+            }
+            FnArg::Typed(_) => return None,
+        };
+        let fields = machine.fields.iter().map(|field| {
+            let ident = &field.ident;
+            let ty = &field.ty;
+            quote!(#ident: ::core::option::Option<#ty>)
+        });
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let types = generics.type_params().map(|tp| &tp.ident);
+        let lifetimes = generics.lifetimes().map(|ld| &ld.lifetime);
+
+        let comment = format!(
+            " the future returned by [`{}::{}`]",
+            ext_trait_name(self.0),
+            ext_method_ident(input)
+        );
+        let vis = &self.0.vis;
+        let future_type = future_type(self.0, input);
+        Some(parse_quote!(
+            #[doc = #comment ]
+            #vis struct #future_type #impl_generics #where_clause {
+                __state: usize,
+                __self: #receiver,
+                #(#fields,)*
+                __marker: ::core::marker::PhantomData<(#(fn(#types),)* #(& #lifetimes (),)*)>,
+            }
+        ))
+    }
+}
+
+pub struct ImplStateMachineFutureExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for ImplStateMachineFutureExpand<'_> {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        if !is_state_machine(input) {
+            return None;
+        }
+        let machine = lower_state_machine(self.0, input).ok()?;
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let mut output = match &input.sig.output {
+            ReturnType::Default => parse_quote!(()),
+            ReturnType::Type(_, ty) => *ty.clone(),
+        };
+        RenameSelf(self.0).visit_type_mut(&mut output);
+
+        let this = match input.sig.inputs.first()? {
+            FnArg::Receiver(r) if r.mutability.is_some() => quote!(&mut *__machine.__self),
+            _ => quote!(&*__machine.__self),
+        };
+        let states = machine.states.iter().enumerate().map(|(i, code)| {
+            quote!(#i => {
+                #(#code)*
+            })
+        });
+
+        let future_type = future_type(self.0, input);
+        let message = format!("`{}` polled after completion", future_type);
+        Some(parse_quote!(
+            impl #impl_generics ::core::future::Future for #future_type #ty_generics #where_clause {
+                type Output = #output;
+
+                #[allow(unused_mut, unused_variables, unreachable_code)]
+                fn poll(mut self: ::core::pin::Pin<&mut Self>, __cx: &mut ::core::task::Context) -> ::core::task::Poll<Self::Output> {
+                    let __machine = &mut *self;
+                    let this = #this;
+                    loop {
+                        match __machine.__state {
+                            #(#states)*
+                            _ => ::core::panic!(#message),
+                        }
+                    }
+                }
+            }
+        ))
+    }
+}
+
+pub struct ImplStateMachineUnpinExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for ImplStateMachineUnpinExpand<'_> {
+    type Output = ItemImpl;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        if !is_state_machine(input) {
+            return None;
+        }
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let generics = ctx.capture(&GenericsExpand(self.0))?;
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        // locals are moved out of their fields while polling, they're never pinned
+        let future_type = future_type(self.0, input);
+        Some(parse_quote!(
+            impl #impl_generics ::core::marker::Unpin for #future_type #ty_generics #where_clause {}
+        ))
+    }
+}
+
+pub struct StateMachineMethodImplExpand<'a>(pub &'a ItemTrait);
+
+impl Expand<TraitItemMethod> for StateMachineMethodImplExpand<'_> {
+    type Output = TraitItemMethod;
+
+    fn expand(&self, input: &TraitItemMethod, c: &mut Collector) -> Option<Self::Output> {
+        input.sig.asyncness?;
+        if !is_state_machine(input) {
+            return None;
+        }
+        let machine = lower_state_machine(self.0, input).ok()?;
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut item = ctx.capture(&ExtMethodExpand(self.0))?;
+
+        let fields = machine.fields.iter().map(|field| {
+            let ident = &field.ident;
+            match &field.arg {
+                Some(arg) => quote!(#ident: ::core::option::Option::Some(#arg)),
+                None => quote!(#ident: ::core::option::Option::None),
+            }
+        });
+        let future_type = future_type(self.0, input);
+        item.default = Some(parse_quote!(
+            {
+                #future_type {
+                    __state: 0,
+                    __self: self,
+                    #(#fields,)*
+                    __marker: ::core::marker::PhantomData,
+                }
+            }
+        ));
+
+        let mut ctx = Context::new(c, item);
+        ctx.capture(&CleanUpMutPatternsExpand)
+    }
+}
@@ -0,0 +1,54 @@
+use quote::format_ident;
+use syn::{
+    parse_quote,
+    visit_mut::{visit_type_mut, VisitMut},
+    FnArg, GenericParam, TraitItem, Type, TypeParam,
+};
+
+/// replaces `impl Trait` arguments with type parameters, the futures have to name them
+pub fn desugar_impl_trait(item: TraitItem) -> TraitItem {
+    let mut m = match item {
+        TraitItem::Method(m) => m,
+        item => return item,
+    };
+
+    let mut desugar = DesugarImplTrait { params: Vec::new() };
+    for arg in m.sig.inputs.iter_mut() {
+        if let FnArg::Typed(pt) = arg {
+            desugar.visit_type_mut(&mut pt.ty);
+        }
+    }
+
+    m.sig
+        .generics
+        .params
+        .extend(desugar.params.into_iter().map(GenericParam::Type));
+
+    TraitItem::Method(m)
+}
+
+struct DesugarImplTrait {
+    params: Vec<TypeParam>,
+}
+
+impl VisitMut for DesugarImplTrait {
+    fn visit_type_mut(&mut self, i: &mut Type) {
+        let mut impl_trait = match i {
+            Type::ImplTrait(impl_trait) => impl_trait.clone(),
+            _ => return visit_type_mut(self, i),
+        };
+
+        // like rustc, the outer `impl Trait` gets the first parameter
+        let idx = self.params.len();
+        let ident = format_ident!("__ImplTrait{}", idx);
+        self.params.push(TypeParam::from(ident.clone()));
+        *i = parse_quote!(#ident);
+
+        for bound in impl_trait.bounds.iter_mut() {
+            self.visit_type_param_bound_mut(bound);
+        }
+        let param = &mut self.params[idx];
+        param.colon_token = Some(Default::default());
+        param.bounds = impl_trait.bounds;
+    }
+}
@@ -1,6 +1,13 @@
-use crate::input::{MethodInput, TraitInput};
+use crate::{
+    input::{MethodInput, TraitInput},
+    methods::{
+        desugar_impl_trait, elide_lifetimes, has_ambiguous_output, has_elided_output,
+        is_typed_receiver, lower_state_machine, mentions_self_type, pinned_receiver,
+        pointer_receiver,
+    },
+};
 use macro_compose::{Collector, Context, Lint};
-use syn::{Error, FnArg, ItemTrait, TraitItem};
+use syn::{Error, FnArg, ItemTrait, ReturnType, TraitItem, TypeParamBound};
 
 pub struct MethodAttrLint;
 
@@ -11,16 +18,62 @@ impl Lint<ItemTrait> for MethodAttrLint {
         for item in input.items.iter() {
             if let TraitItem::Method(m) = item {
                 if attrs.dynamic.is_some() {
+                    if let ReturnType::Type(_, ty) = &m.sig.output {
+                        if mentions_self_type(ty) {
+                            c.error(Error::new_spanned(
+                                ty,
+                                "dynamic traits can't return `Self`, the futures don't know the implementing type",
+                            ));
+                        }
+                    }
+
                     if let Some(FnArg::Receiver(r)) = m.sig.inputs.first() {
                         if r.reference.is_none() {
                             c.error(Error::new_spanned(
                                 r,
                                 "dynamic traits can't receive owned self",
                             ));
+                        } else if MethodInput::from(m.attrs.as_slice()).boxed.is_some() {
+                            // the future holds a reference to the trait object
+                            let (bound, option) = if r.mutability.is_some() {
+                                ("Send", attrs.send)
+                            } else {
+                                ("Sync", attrs.sync)
+                            };
+                            if option.is_none() && !has_supertrait(input, bound) {
+                                c.error(Error::new_spanned(
+                                    r,
+                                    format!(
+                                        "`boxed` futures are `Send`, which needs `dynamic({})` or a `{}` supertrait, use `boxed_local` otherwise",
+                                        bound.to_lowercase(),
+                                        bound
+                                    ),
+                                ));
+                            }
                         }
                     }
                 }
 
+                if let Some(receiver) = m.sig.inputs.first() {
+                    if is_typed_receiver(receiver)
+                        && pinned_receiver(receiver).is_none()
+                        && pointer_receiver(receiver).is_none()
+                    {
+                        c.error(Error::new_spanned(
+                            receiver,
+                            "unsupported receiver, expected `self: Pin<&mut Self>`, `self: Box<Self>`, `self: Rc<Self>`, `self: Arc<Self>`, `self: &Rc<Self>` or `self: &Arc<Self>`",
+                        ));
+                    }
+                }
+
+                if has_ambiguous_output(m) {
+                    c.error(Error::new_spanned(
+                        &m.sig.output,
+                        "missing lifetime specifier, the return type doesn't say which argument it borrows from",
+                    ));
+                }
+
+                let mut state_machine = false;
                 let mut subcontext = Context::new_by_ref(c, &m.attrs);
                 if subcontext.lint(MethodInput::lint()) {
                     let input = MethodInput::from(m.attrs.as_slice());
@@ -31,12 +84,156 @@ impl Lint<ItemTrait> for MethodAttrLint {
                         ));
                     } else if input.provided.is_some() && m.default.is_none() {
                         c.error(Error::new_spanned(
-                            &m,
+                            m,
                             "provided methods must have a default block",
                         ));
+                    } else if input.provided.is_none()
+                        && (input.boxed.is_some() || input.boxed_local.is_some())
+                    {
+                        c.error(Error::new_spanned(
+                            m,
+                            "only provided methods can return boxed futures",
+                        ));
+                    } else if input.provided.is_none() && input.state_machine.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "only provided methods can be compiled into state machines",
+                        ));
+                    } else if input.provided.is_none() && input.overridable.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "only provided methods can be overridden",
+                        ));
+                    } else if input.boxed.is_some() && input.boxed_local.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "`boxed` and `boxed_local` can't be combined",
+                        ));
+                    } else if input.state_machine.is_some()
+                        && (input.boxed.is_some() || input.boxed_local.is_some())
+                    {
+                        c.error(Error::new_spanned(m, "state machines can't be boxed"));
+                    } else if input.overridable.is_some()
+                        && (input.state_machine.is_some()
+                            || (attrs.rpitit.is_none()
+                                && input.boxed.is_none()
+                                && input.boxed_local.is_none()))
+                    {
+                        c.error(Error::new_spanned(
+                            m,
+                            "overridable methods have to return boxed futures or `impl Future`, use `boxed`, `boxed_local` or `#[async_trait_ext(rpitit)]`",
+                        ));
+                    } else if input.provided.is_some() && input.take_args.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "provided methods can't take their arguments",
+                        ));
+                    } else if input.provided.is_some() && input.state.is_some() {
+                        c.error(Error::new_spanned(m, "provided methods can't have a state"));
+                    } else if input.provided.is_some() && input.on_cancel.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "provided methods can't have a cancellation hook",
+                        ));
+                    } else if input.provided.is_some() && input.owned.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "provided methods can't have owned variants",
+                        ));
+                    } else if input.owned.is_some()
+                        && !matches!(m.sig.inputs.first(), Some(FnArg::Receiver(r)) if r.reference.is_some())
+                    {
+                        c.error(Error::new_spanned(
+                            m,
+                            "owned variants require a `&self` or `&mut self` receiver",
+                        ));
+                    } else if input.owned.is_some() && has_elided_output(m) {
+                        c.error(Error::new_spanned(
+                            &m.sig.output,
+                            "owned variants can't return borrowed values",
+                        ));
+                    } else if input.provided.is_some()
+                        && m.sig.inputs.first().is_some_and(is_typed_receiver)
+                    {
+                        c.error(Error::new_spanned(
+                            m,
+                            "provided methods can't have typed receivers",
+                        ));
+                    } else if attrs.gat.is_some()
+                        && (input.take_args.is_some()
+                            || input.state.is_some()
+                            || input.on_cancel.is_some()
+                            || input.owned.is_some())
+                    {
+                        c.error(Error::new_spanned(
+                            m,
+                            "`take_args`, `state`, `on_cancel` and `owned` need poll methods, gat traits don't have them",
+                        ));
+                    } else if attrs.gat.is_some() && input.state_machine.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "state machines need poll methods, gat traits don't have them",
+                        ));
+                    } else if m.sig.asyncness.is_none()
+                        && (input.poll.is_some() || input.future.is_some())
+                    {
+                        c.error(Error::new_spanned(
+                            m,
+                            "only async methods have poll methods and futures",
+                        ));
+                    } else if input.provided.is_some() && input.poll.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "provided methods don't have poll methods",
+                        ));
+                    } else if attrs.gat.is_some() && input.poll.is_some() {
+                        c.error(Error::new_spanned(
+                            m,
+                            "gat traits don't have poll methods, their futures can be named with `future`",
+                        ));
+                    } else if attrs.rpitit.is_some()
+                        && input.provided.is_some()
+                        && input.future.is_some()
+                        && input.boxed.is_none()
+                        && input.boxed_local.is_none()
+                        && input.state_machine.is_none()
+                    {
+                        c.error(Error::new_spanned(
+                            m,
+                            "provided methods of rpitit traits return `impl Future`, only boxed futures and state machines have a name",
+                        ));
+                    }
+
+                    if let Some(Err(e)) = MethodInput::state(&m.attrs) {
+                        c.error(e);
+                    }
+                    if let Some(Err(e)) = MethodInput::poll(&m.attrs) {
+                        c.error(e);
+                    }
+                    if let Some(Err(e)) = MethodInput::future(&m.attrs) {
+                        c.error(e);
+                    }
+
+                    state_machine = input.provided.is_some() && input.state_machine.is_some();
+                }
+
+                // the expansion sees the method with explicit lifetimes and desugared `impl Trait`
+                if state_machine {
+                    if let TraitItem::Method(m) = desugar_impl_trait(elide_lifetimes(item.clone()))
+                    {
+                        if let Err(e) = lower_state_machine(input, &m) {
+                            c.error(e);
+                        }
                     }
                 }
             }
         }
     }
 }
+
+fn has_supertrait(input: &ItemTrait, ident: &str) -> bool {
+    input.supertraits.iter().any(|bound| {
+        matches!(bound, TypeParamBound::Trait(tb)
+            if tb.path.segments.last().is_some_and(|s| s.ident == ident))
+    })
+}
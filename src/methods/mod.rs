@@ -1,18 +1,468 @@
+mod elision;
 mod expand;
+mod impl_trait;
 mod lint;
+mod native;
+mod state_machine;
 
+pub use elision::*;
 pub use expand::*;
+pub use impl_trait::*;
 pub use lint::*;
+pub use native::*;
+pub use state_machine::*;
 
+use crate::input::{Forward, MethodInput, TraitInput};
 use heck::CamelCase;
-use quote::format_ident;
-use syn::{Ident, ItemTrait, TraitItemMethod};
+use quote::{format_ident, quote, ToTokens};
+use std::{fmt, iter::once};
+use syn::{
+    parse_quote,
+    visit_mut::{visit_ident_mut, visit_path_mut, visit_type_mut, VisitMut},
+    FnArg, GenericArgument, GenericParam, Ident, ItemTrait, Pat, PathArguments, PredicateType,
+    ReturnType, TraitItem, TraitItemMethod, TraitItemType, Type, TypeParamBound, TypeParen,
+    TypePath, TypeReference, WherePredicate,
+};
 
 fn future_type(item: &ItemTrait, method: &TraitItemMethod) -> Ident {
+    future_ident(item, method, "{trait}{method}")
+}
+
+fn gat_ident(item: &ItemTrait, method: &TraitItemMethod) -> Ident {
+    future_ident(item, method, "{method}Future")
+}
+
+/// the name passed as `#[async_fn(future = "...")]`, or the filled in template
+fn future_ident(item: &ItemTrait, method: &TraitItemMethod, default: &str) -> Ident {
+    if let Some(Ok(ident)) = MethodInput::future(&method.attrs) {
+        return if is_owned_variant(method) {
+            format_ident!("{}Owned", ident)
+        } else {
+            ident
+        };
+    }
+
+    // the template is checked by the lint
+    let template = TraitInput::from(item.attrs.as_slice()).future;
+    let name = template
+        .as_deref()
+        .unwrap_or(default)
+        .replace("{trait}", &item.ident.to_string())
+        .replace(
+            "{method}",
+            &ext_method_ident(method).to_string().to_camel_case(),
+        );
+    format_ident!("{}", name, span = method.sig.ident.span())
+}
+
+/// the name of the poll method, `poll_x` unless passed as `#[async_fn(poll = "...")]`
+pub fn poll_method_ident(method: &TraitItemMethod) -> Ident {
+    match MethodInput::poll(&method.attrs) {
+        Some(Ok(ident)) => ident,
+        _ => format_ident!("poll_{}", method.sig.ident),
+    }
+}
+
+/// the name of the method in the extension trait
+fn ext_method_ident(method: &TraitItemMethod) -> Ident {
+    if is_owned_variant(method) {
+        format_ident!("{}_owned", method.sig.ident, span = method.sig.ident.span())
+    } else {
+        method.sig.ident.clone()
+    }
+}
+
+/// the item followed by the `x_owned` variant of methods marked with `#[async_fn(owned)]`
+pub fn with_owned_variant(item: TraitItem) -> impl Iterator<Item = TraitItem> {
+    let variant = match &item {
+        TraitItem::Method(m) => owned_variant(m).map(TraitItem::Method),
+        _ => None,
+    };
+    once(item).chain(variant)
+}
+
+/// the method with its receiver replaced by `self: Arc<Self>` or `self: Box<Self>`
+fn owned_variant(method: &TraitItemMethod) -> Option<TraitItemMethod> {
+    MethodInput::from(method.attrs.as_slice()).owned?;
+    let receiver = match method.sig.inputs.first()? {
+        FnArg::Receiver(r) if r.reference.is_some() => r,
+        _ => return None,
+    };
+
+    let mut variant = method.clone();
+    variant.sig.inputs[0] = if receiver.mutability.is_some() {
+        parse_quote!(self: ::std::boxed::Box<Self>)
+    } else {
+        parse_quote!(self: ::std::sync::Arc<Self>)
+    };
+    Some(variant)
+}
+
+/// checks if a method was generated by `owned_variant`
+fn is_owned_variant(method: &TraitItemMethod) -> bool {
+    MethodInput::from(method.attrs.as_slice()).owned.is_some()
+        && method.sig.inputs.first().is_some_and(is_typed_receiver)
+}
+
+/// checks if the futures refuse to be polled after completion
+fn is_fused(item: &ItemTrait) -> bool {
+    TraitInput::from(item.attrs.as_slice()).fused.is_some()
+}
+
+/// checks if provided methods return `impl Future` instead of a type alias
+fn is_rpitit(item: &ItemTrait) -> bool {
+    TraitInput::from(item.attrs.as_slice()).rpitit.is_some()
+}
+
+pub fn is_gat_method(item: &ItemTrait, method: &TraitItemMethod) -> bool {
+    TraitInput::from(item.attrs.as_slice()).gat.is_some()
+        && method.sig.asyncness.is_some()
+        && method.default.is_none()
+}
+
+/// checks if a provided method returns a boxed future
+pub fn is_boxed(method: &TraitItemMethod) -> bool {
+    let attrs = MethodInput::from(method.attrs.as_slice());
+    attrs.boxed.is_some() || attrs.boxed_local.is_some()
+}
+
+fn is_state_machine(method: &TraitItemMethod) -> bool {
+    MethodInput::from(method.attrs.as_slice())
+        .state_machine
+        .is_some()
+}
+
+pub fn is_overridable(method: &TraitItemMethod) -> bool {
+    MethodInput::from(method.attrs.as_slice())
+        .overridable
+        .is_some()
+}
+
+fn provided_method_ident(method: &TraitItemMethod) -> Ident {
     format_ident!(
-        "{}{}",
-        item.ident,
-        method.sig.ident.to_string().to_camel_case(),
+        "provided_{}",
+        method.sig.ident,
         span = method.sig.ident.span()
     )
 }
+
+fn takes_args(method: &TraitItemMethod) -> bool {
+    MethodInput::from(method.attrs.as_slice())
+        .take_args
+        .is_some()
+}
+
+fn has_cancel_hook(method: &TraitItemMethod) -> bool {
+    MethodInput::from(method.attrs.as_slice())
+        .on_cancel
+        .is_some()
+}
+
+/// the type passed as `#[async_fn(state = "...")]`
+fn state_type(method: &TraitItemMethod) -> Option<Type> {
+    MethodInput::state(&method.attrs).and_then(Result::ok)
+}
+
+/// checks if an argument is moved into the future by `#[async_fn(take_args)]`
+fn is_owned(arg: &FnArg) -> bool {
+    match arg {
+        FnArg::Receiver(r) => r.reference.is_none(),
+        FnArg::Typed(pt) => !is_typed_receiver(arg) && !matches!(&*pt.ty, Type::Reference(_)),
+    }
+}
+
+/// checks if an argument is a `self` receiver with an explicit type
+fn is_typed_receiver(arg: &FnArg) -> bool {
+    match arg {
+        FnArg::Typed(pt) => matches!(&*pt.pat, Pat::Ident(pi) if pi.ident == "self"),
+        FnArg::Receiver(_) => false,
+    }
+}
+
+/// returns the `&mut Self` of a `self: Pin<&mut Self>` receiver
+fn pinned_receiver(arg: &FnArg) -> Option<&TypeReference> {
+    let pt = match arg {
+        FnArg::Typed(pt) if is_typed_receiver(arg) => pt,
+        _ => return None,
+    };
+
+    let reference = match pointee(&pt.ty, "Pin")? {
+        Type::Reference(reference) => reference,
+        _ => return None,
+    };
+
+    if reference.mutability.is_some() && is_self(&reference.elem) {
+        Some(reference)
+    } else {
+        None
+    }
+}
+
+/// a `self: Box<Self>`, `self: Rc<Self>`, `self: Arc<Self>`, `self: &Rc<Self>` or
+/// `self: &Arc<Self>` receiver
+struct PointerReceiver<'a> {
+    /// the type of the pointer stored in the future
+    ty: &'a Type,
+    /// whether the pointer is borrowed and has to be cloned
+    by_ref: bool,
+    /// whether the poll method gets `&mut self` instead of `&self`
+    unique: bool,
+}
+
+fn pointer_receiver(arg: &FnArg) -> Option<PointerReceiver<'_>> {
+    let pt = match arg {
+        FnArg::Typed(pt) if is_typed_receiver(arg) => pt,
+        _ => return None,
+    };
+
+    let (ty, by_ref) = match &*pt.ty {
+        Type::Reference(reference) if reference.mutability.is_none() => (&*reference.elem, true),
+        ty => (ty, false),
+    };
+
+    let unique = if pointee(ty, "Box").is_some_and(is_self) {
+        true
+    } else if pointee(ty, "Rc")
+        .or_else(|| pointee(ty, "Arc"))
+        .is_some_and(is_self)
+    {
+        false
+    } else {
+        return None;
+    };
+
+    if unique && by_ref {
+        return None;
+    }
+
+    Some(PointerReceiver { ty, by_ref, unique })
+}
+
+/// how a method of the poll trait borrows `self`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ForwardedReceiver {
+    Shared,
+    Unique,
+    Pinned,
+}
+
+impl ForwardedReceiver {
+    pub fn forwarded_through(self, forward: Forward) -> bool {
+        match self {
+            ForwardedReceiver::Shared => true,
+            ForwardedReceiver::Unique => matches!(forward, Forward::RefMut | Forward::Box),
+            ForwardedReceiver::Pinned => {
+                matches!(forward, Forward::RefMut | Forward::Box | Forward::Pin)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ForwardedReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ForwardedReceiver::Shared => "`&self`",
+            ForwardedReceiver::Unique => "`&mut self`",
+            ForwardedReceiver::Pinned => "`self: Pin<&mut Self>`",
+        })
+    }
+}
+
+/// the receiver of a method that can be forwarded, `Self: Sized` is fine if the target is `sized`
+pub fn forwarded_receiver(method: &TraitItemMethod, sized: bool) -> Option<ForwardedReceiver> {
+    let receiver = match method.sig.inputs.first()? {
+        FnArg::Receiver(r) if r.reference.is_some() && r.mutability.is_some() => {
+            ForwardedReceiver::Unique
+        }
+        FnArg::Receiver(r) if r.reference.is_some() => ForwardedReceiver::Shared,
+        arg if pinned_receiver(arg).is_some() => ForwardedReceiver::Pinned,
+        _ => return None,
+    };
+
+    let args = method.sig.inputs.iter().skip(1).any(|arg| match arg {
+        FnArg::Typed(pt) => mentions_self_type(&pt.ty),
+        FnArg::Receiver(_) => true,
+    });
+    let output = match &method.sig.output {
+        ReturnType::Type(_, ty) => mentions_self_type(ty),
+        ReturnType::Default => false,
+    };
+    let predicates = method.sig.generics.where_clause.iter().any(|where_clause| {
+        where_clause.predicates.iter().any(|predicate| {
+            matches!(predicate, WherePredicate::Type(pt)
+                if mentions_self_type(&pt.bounded_ty) && !(sized && is_sized_bound(pt)))
+        })
+    });
+
+    if args || output || predicates {
+        None
+    } else {
+        Some(receiver)
+    }
+}
+
+fn is_sized_bound(predicate: &PredicateType) -> bool {
+    is_self(&predicate.bounded_ty)
+        && predicate.bounds.iter().all(|bound| {
+            matches!(bound, TypeParamBound::Trait(tb)
+                if tb.path.segments.last().is_some_and(|s| s.ident == "Sized"))
+        })
+}
+
+/// returns `T` if `ty` is `Pointer<T>`
+fn pointee<'a>(ty: &'a Type, pointer: &str) -> Option<&'a Type> {
+    let path = match ty {
+        Type::Path(tp) if tp.qself.is_none() => &tp.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != pointer {
+        return None;
+    }
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => args,
+        _ => return None,
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+fn is_self(ty: &Type) -> bool {
+    matches!(ty, Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident("Self"))
+}
+
+/// the associated types of a trait
+pub fn assoc_types(item: &ItemTrait) -> impl Iterator<Item = &TraitItemType> {
+    item.items.iter().filter_map(|item| match item {
+        TraitItem::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// the generic parameter standing in for an associated type of a dynamic trait
+pub fn assoc_type_param(ident: &Ident) -> Ident {
+    format_ident!("__{}", ident, span = ident.span())
+}
+
+/// returns `Item` if the type is `Self::Item` or `<Self as Trait>::Item` for an associated type
+/// of the trait
+fn self_assoc_type<'a>(item: &ItemTrait, ty: &'a TypePath) -> Option<&'a Ident> {
+    let segments = &ty.path.segments;
+    let segment = match &ty.qself {
+        None if segments.len() == 2 && segments[0].ident == "Self" => &segments[1],
+        Some(qself) if qself.position + 1 == segments.len() && is_self(&qself.ty) => {
+            segments.last()?
+        }
+        _ => return None,
+    };
+
+    if segment.arguments.is_empty() && assoc_types(item).any(|ty| ty.ident == segment.ident) {
+        Some(&segment.ident)
+    } else {
+        None
+    }
+}
+
+/// the trait object `dyn Trait<.., Item = __Item>` with the auto traits of `dynamic(send, sync)`
+pub fn dyn_trait(item: &ItemTrait, ident: &Ident) -> Type {
+    let bound = trait_bound(item, ident);
+    let attrs = TraitInput::from(item.attrs.as_slice());
+    let send = attrs
+        .send
+        .map(|_| -> TypeParamBound { parse_quote!(::core::marker::Send) });
+    let sync = attrs
+        .sync
+        .map(|_| -> TypeParamBound { parse_quote!(::core::marker::Sync) });
+    let auto_traits = send.into_iter().chain(sync);
+    parse_quote!(dyn #bound #(+ #auto_traits)*)
+}
+
+pub fn trait_bound(item: &ItemTrait, ident: &Ident) -> TypeParamBound {
+    let params = item.generics.params.iter().map(|param| match param {
+        GenericParam::Type(tp) => tp.ident.to_token_stream(),
+        GenericParam::Lifetime(ld) => ld.lifetime.to_token_stream(),
+        GenericParam::Const(cp) => cp.ident.to_token_stream(),
+    });
+    let bindings = assoc_types(item).map(|ty| {
+        let ident = &ty.ident;
+        let param = assoc_type_param(ident);
+        quote!(#ident = #param)
+    });
+    let args: Vec<_> = params.chain(bindings).collect();
+
+    if args.is_empty() {
+        parse_quote!(#ident)
+    } else {
+        parse_quote!(#ident<#(#args),*>)
+    }
+}
+
+/// references to trait objects with auto traits need parentheses: `&mut (dyn Trait + Send)`
+fn referenced(ty: Type) -> Type {
+    match ty {
+        Type::TraitObject(object) if object.bounds.len() > 1 => Type::Paren(TypeParen {
+            paren_token: Default::default(),
+            elem: Box::new(Type::TraitObject(object)),
+        }),
+        ty => ty,
+    }
+}
+
+/// checks if a type uses `Self` itself, not just one of its associated types
+fn mentions_self_type(ty: &Type) -> bool {
+    struct MentionsSelfType(bool);
+
+    impl VisitMut for MentionsSelfType {
+        fn visit_type_mut(&mut self, i: &mut Type) {
+            match i {
+                Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident("Self") => self.0 = true,
+                // `<Self as Trait>::Item`
+                Type::Path(tp) if tp.qself.as_ref().is_some_and(|q| is_self(&q.ty)) => {
+                    visit_path_mut(self, &mut tp.path)
+                }
+                _ => visit_type_mut(self, i),
+            }
+        }
+    }
+
+    let mut visitor = MentionsSelfType(false);
+    visitor.visit_type_mut(&mut ty.clone());
+    visitor.0
+}
+
+fn mentions_self(ty: &Type) -> bool {
+    struct MentionsSelf(bool);
+
+    impl VisitMut for MentionsSelf {
+        fn visit_ident_mut(&mut self, i: &mut Ident) {
+            self.0 |= i == "Self";
+            visit_ident_mut(self, i);
+        }
+    }
+
+    let mut visitor = MentionsSelf(false);
+    visitor.visit_type_mut(&mut ty.clone());
+    visitor.0
+}
+
+/// whether the method returns an `impl Trait`, which is a different type for every implementor
+pub fn returns_impl_trait(method: &TraitItemMethod) -> bool {
+    struct ImplTrait(bool);
+
+    impl VisitMut for ImplTrait {
+        fn visit_type_mut(&mut self, i: &mut Type) {
+            match i {
+                Type::ImplTrait(_) => self.0 = true,
+                _ => visit_type_mut(self, i),
+            }
+        }
+    }
+
+    let mut visitor = ImplTrait(false);
+    if let ReturnType::Type(_, ty) = &method.sig.output {
+        visitor.visit_type_mut(&mut ty.clone());
+    }
+    visitor.0
+}
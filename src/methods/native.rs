@@ -0,0 +1,118 @@
+use crate::{
+    ext_trait_name,
+    input::TraitInput,
+    methods::{
+        ext_method_ident, is_gat_method, is_state_machine, pinned_receiver, with_owned_variant,
+    },
+};
+use syn::{
+    parse_quote,
+    visit_mut::{visit_expr_mut, VisitMut},
+    Expr, FnArg, Ident, Item, ItemTrait, Path, Receiver, TraitItem,
+};
+
+/// replaces `self.read(..)` with `FooExt::read(self, ..)`, the other traits make it ambiguous
+pub fn qualify_calls(trait_item: &ItemTrait, item: TraitItem) -> TraitItem {
+    let attrs = TraitInput::from(trait_item.attrs.as_slice());
+    if attrs.native.is_none() && attrs.dyn_trait.is_none() {
+        return item;
+    }
+    let mut m = match item {
+        // state machines await the poll methods
+        TraitItem::Method(m) if m.default.is_some() && !is_state_machine(&m) => m,
+        item => return item,
+    };
+
+    let (_, ty_generics, _) = trait_item.generics.split_for_impl();
+    let turbofish = ty_generics.as_turbofish();
+    let trait_ident = &trait_item.ident;
+    let ext_ident = ext_trait_name(trait_item);
+    let methods = trait_item
+        .items
+        .iter()
+        .cloned()
+        .flat_map(with_owned_variant)
+        .filter_map(|item| match item {
+            // the native methods with pinned receivers aren't found on `&mut self`
+            TraitItem::Method(m)
+                if m.sig.asyncness.is_some()
+                    && m.sig.inputs.first().and_then(pinned_receiver).is_none() =>
+            {
+                let path = if is_gat_method(trait_item, &m) {
+                    parse_quote!(#trait_ident #turbofish)
+                } else {
+                    parse_quote!(#ext_ident #turbofish)
+                };
+                let receiver = match m.sig.inputs.first() {
+                    Some(FnArg::Receiver(r)) => Some(r.clone()),
+                    _ => None,
+                };
+                Some(Method {
+                    ident: ext_method_ident(&m),
+                    path,
+                    receiver,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    // an owned `self` has to be borrowed like the method call would
+    let owned_self =
+        matches!(m.sig.inputs.first(), Some(FnArg::Receiver(r)) if r.reference.is_none());
+    if let Some(default) = &mut m.default {
+        QualifyCalls {
+            methods,
+            owned_self,
+        }
+        .visit_block_mut(default);
+    }
+
+    TraitItem::Method(m)
+}
+
+struct Method {
+    ident: Ident,
+    path: Path,
+    receiver: Option<Receiver>,
+}
+
+struct QualifyCalls {
+    methods: Vec<Method>,
+    owned_self: bool,
+}
+
+impl VisitMut for QualifyCalls {
+    fn visit_expr_mut(&mut self, i: &mut Expr) {
+        visit_expr_mut(self, i);
+
+        let call = match i {
+            Expr::MethodCall(call) => call,
+            _ => return,
+        };
+        if !matches!(&*call.receiver, Expr::Path(p) if p.path.is_ident("self")) {
+            return;
+        }
+        let method = match self.methods.iter().find(|m| m.ident == call.method) {
+            Some(method) => method,
+            None => return,
+        };
+
+        let receiver: Expr = match &method.receiver {
+            Some(Receiver {
+                reference: Some(_),
+                mutability,
+                ..
+            }) if self.owned_self => parse_quote!(& #mutability self),
+            _ => parse_quote!(self),
+        };
+        let path = &method.path;
+        let ident = &call.method;
+        let turbofish = &call.turbofish;
+        let args = &call.args;
+        *i = parse_quote!(#path::#ident #turbofish(#receiver, #args));
+    }
+
+    // nested items have their own `self`
+    fn visit_item_mut(&mut self, _: &mut Item) {}
+}
@@ -0,0 +1,1118 @@
+use crate::methods::{
+    has_cancel_hook, poll_method_ident, state_type, takes_args, trait_method_path, RenameSelf,
+};
+use proc_macro2::{Group, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use std::{iter::once, mem};
+use syn::{
+    parse_quote,
+    visit_mut::{
+        visit_expr_mut, visit_macro_mut, visit_pat_ident_mut, visit_type_reference_mut, VisitMut,
+    },
+    Block, Error, Expr, ExprAwait, ExprIf, ExprLoop, ExprMatch, ExprMethodCall, ExprWhile, FnArg,
+    GenericArgument, Ident, Item, ItemTrait, Lifetime, Local, Macro, Pat, PatIdent, PathArguments,
+    Result, ReturnType, Stmt, Token, TraitItem, TraitItemMethod, Type, TypeReference,
+};
+
+pub struct StateMachine {
+    pub fields: Vec<StateMachineField>,
+    pub states: Vec<Vec<Stmt>>,
+}
+
+pub struct StateMachineField {
+    pub ident: Ident,
+    pub ty: Type,
+    pub arg: Option<Ident>,
+}
+
+/// lowers the body of a `#[async_fn(provided, state_machine)]` method
+pub fn lower_state_machine(item: &ItemTrait, method: &TraitItemMethod) -> Result<StateMachine> {
+    match method.sig.inputs.first() {
+        Some(FnArg::Receiver(r)) if r.reference.is_some() => {}
+        _ => {
+            return Err(Error::new_spanned(
+                &method.sig,
+                "state machines require a `&self` or `&mut self` receiver",
+            ))
+        }
+    }
+
+    let mut lowering = Lowering {
+        item,
+        method,
+        fields: Vec::new(),
+        locals: Vec::new(),
+        scope: Vec::new(),
+        block: 0,
+        states: Vec::new(),
+        loops: Vec::new(),
+        awaits: 0,
+    };
+
+    for arg in method.sig.inputs.iter().skip(1) {
+        let pt = match arg {
+            FnArg::Typed(pt) => pt,
+            FnArg::Receiver(_) => unreachable!(),
+        };
+        let pi = match &*pt.pat {
+            Pat::Ident(pi) if pi.by_ref.is_none() && pi.subpat.is_none() => pi,
+            pat => {
+                return Err(Error::new_spanned(
+                    pat,
+                    "state machines don't support patterns in arguments",
+                ))
+            }
+        };
+        let field = lowering.store(pi, *pt.ty.clone());
+        lowering.fields[field].arg = Some(pi.ident.clone());
+    }
+
+    let body = match &method.default {
+        Some(body) => &body.stmts,
+        None => return Err(Error::new_spanned(method, "state machines need a body")),
+    };
+    let start = lowering.new_state(&idents(quote!(#(#body)*)));
+    let code = lowering.lower_stmts(body, Next::Return, &[])?;
+    lowering.states[start].code = code;
+
+    let Lowering {
+        fields,
+        locals,
+        states,
+        ..
+    } = lowering;
+    let states = states
+        .into_iter()
+        .map(|state| {
+            let loads = state.entry.iter().map(|&local| -> Stmt {
+                let Stored {
+                    ident,
+                    mutability,
+                    field,
+                } = &locals[local];
+                let field = &fields[*field].ident;
+                parse_quote!(
+                    let #mutability #ident = ::core::option::Option::take(&mut __machine.#field).unwrap();
+                )
+            });
+            loads.chain(state.code).collect()
+        })
+        .collect();
+
+    Ok(StateMachine { fields, states })
+}
+
+#[derive(Clone, Copy)]
+enum Next {
+    Goto(usize),
+    Return,
+}
+
+struct State {
+    entry: Vec<usize>,
+    code: Vec<Stmt>,
+}
+
+struct Stored {
+    ident: Ident,
+    mutability: Option<Token![mut]>,
+    field: usize,
+}
+
+struct Lowering<'a> {
+    item: &'a ItemTrait,
+    method: &'a TraitItemMethod,
+    fields: Vec<StateMachineField>,
+    locals: Vec<Stored>,
+    scope: Vec<usize>,
+    block: usize,
+    states: Vec<State>,
+    loops: Vec<(usize, usize)>,
+    awaits: usize,
+}
+
+impl<'a> Lowering<'a> {
+    fn new_state(&mut self, live: &[Ident]) -> usize {
+        let entry = self
+            .scope
+            .iter()
+            .copied()
+            .filter(|&local| live.contains(&self.locals[local].ident))
+            .collect();
+        self.states.push(State {
+            entry,
+            code: Vec::new(),
+        });
+        self.states.len() - 1
+    }
+
+    fn add_field(&mut self, ident: Ident, mut ty: Type) -> usize {
+        RenameSelf(self.item).visit_type_mut(&mut ty);
+        self.fields.push(StateMachineField {
+            ident,
+            ty,
+            arg: None,
+        });
+        self.fields.len() - 1
+    }
+
+    fn store(&mut self, pi: &PatIdent, ty: Type) -> usize {
+        let field = self.add_field(format_ident!("__local{}", self.locals.len()), ty);
+        self.locals.push(Stored {
+            ident: pi.ident.clone(),
+            mutability: pi.mutability,
+            field,
+        });
+        self.scope.push(self.locals.len() - 1);
+        field
+    }
+
+    /// shadowing a stored local of an outer block would mix up the two across states
+    fn shadow(&mut self, ident: &Ident) -> Result<()> {
+        let pos = self
+            .scope
+            .iter()
+            .position(|&local| self.locals[local].ident == *ident);
+        match pos {
+            Some(pos) if pos >= self.block => {
+                self.scope.remove(pos);
+                Ok(())
+            }
+            Some(_) => Err(shadow_error(ident)),
+            None => Ok(()),
+        }
+    }
+
+    fn goto(&self, state: usize) -> Expr {
+        let stores = self.stores(state);
+        parse_quote!({
+            #(#stores)*
+            __machine.__state = #state;
+            continue;
+        })
+    }
+
+    fn stores(&self, state: usize) -> Vec<Stmt> {
+        self.states[state]
+            .entry
+            .iter()
+            .map(|&local| {
+                let Stored { ident, field, .. } = &self.locals[local];
+                let field = &self.fields[*field].ident;
+                parse_quote!(__machine.#field = ::core::option::Option::Some(#ident);)
+            })
+            .collect()
+    }
+
+    fn finish(&self, next: Next, value: Option<&Expr>) -> Result<Vec<Stmt>> {
+        let value = value.map(|value| self.rewrite_expr(value)).transpose()?;
+        Ok(match next {
+            Next::Return => {
+                let value = value.unwrap_or_else(|| parse_quote!(()));
+                vec![Stmt::Expr(ret(&value))]
+            }
+            Next::Goto(state) => value
+                .map(|value| parse_quote!(#value;))
+                .into_iter()
+                .chain(once(Stmt::Expr(self.goto(state))))
+                .collect(),
+        })
+    }
+
+    fn lower_block(&mut self, stmts: &[Stmt], next: Next, live: &[Ident]) -> Result<Vec<Stmt>> {
+        let block = mem::replace(&mut self.block, self.scope.len());
+        let code = self.lower_stmts(stmts, next, live);
+        self.block = block;
+        code
+    }
+
+    fn lower_stmts(&mut self, stmts: &[Stmt], next: Next, live: &[Ident]) -> Result<Vec<Stmt>> {
+        let depth = self.scope.len();
+        let mut code = Vec::new();
+
+        for (i, stmt) in stmts.iter().enumerate() {
+            let rest = &stmts[i + 1..];
+
+            if stmt_await(stmt).is_none() {
+                match stmt {
+                    Stmt::Local(local) => code.push(self.lower_let(local, rest)?),
+                    Stmt::Expr(expr) if rest.is_empty() => {
+                        self.scope.truncate(depth);
+                        code.extend(self.finish(next, Some(expr))?);
+                        return Ok(code);
+                    }
+                    stmt => code.push(self.rewrite_stmt(stmt)?),
+                }
+                continue;
+            }
+
+            let live_after: Vec<Ident> = idents(quote!(#(#rest)*))
+                .into_iter()
+                .chain(live.to_vec())
+                .collect();
+            match stmt {
+                Stmt::Local(local) => {
+                    let expr = &local.init.as_ref().unwrap().1;
+                    let (call, is_try) = await_call(expr)?;
+
+                    let await_live: Vec<Ident> =
+                        idents(stmt).into_iter().chain(live_after).collect();
+                    let (start, state) = self.start_await(call, &await_live)?;
+                    code.extend(start);
+
+                    let mut state_code = mem::take(&mut self.states[state].code);
+                    let value = self.await_value(is_try, expr)?;
+                    let ty = self.awaited_output(call, is_try);
+                    state_code.push(self.bind(local, Some(value), ty, rest)?);
+                    state_code.extend(self.lower_stmts(rest, next, live)?);
+                    self.states[state].code = state_code;
+                }
+                Stmt::Expr(expr) | Stmt::Semi(expr, _) => {
+                    let tail = matches!(stmt, Stmt::Expr(_)) && rest.is_empty();
+
+                    if is_await_call(expr) {
+                        let (call, is_try) = await_call(expr)?;
+
+                        let await_live: Vec<Ident> =
+                            idents(stmt).into_iter().chain(live_after).collect();
+                        let (start, state) = self.start_await(call, &await_live)?;
+                        code.extend(start);
+
+                        let mut state_code = mem::take(&mut self.states[state].code);
+                        let value = self.await_value(is_try, expr)?;
+                        if tail {
+                            state_code.extend(self.finish(next, Some(&value))?);
+                        } else {
+                            state_code.push(parse_quote!(#value;));
+                            state_code.extend(self.lower_stmts(rest, next, live)?);
+                        }
+                        self.states[state].code = state_code;
+                    } else if tail {
+                        code.extend(self.lower_control(expr, next, live)?);
+                    } else {
+                        let join = self.new_state(&live_after);
+                        let join_code = self.lower_stmts(rest, next, live)?;
+                        self.states[join].code = join_code;
+
+                        code.extend(self.lower_control(expr, Next::Goto(join), &live_after)?);
+                    }
+                }
+                Stmt::Item(_) => unreachable!(),
+            }
+
+            self.scope.truncate(depth);
+            return Ok(code);
+        }
+
+        self.scope.truncate(depth);
+        code.extend(self.finish(next, None)?);
+        Ok(code)
+    }
+
+    fn lower_control(&mut self, expr: &Expr, next: Next, live: &[Ident]) -> Result<Vec<Stmt>> {
+        match expr {
+            Expr::If(ei) => Ok(vec![Stmt::Expr(self.lower_if(ei, next, live)?)]),
+            Expr::Match(em) => Ok(vec![Stmt::Expr(self.lower_match(em, next, live)?)]),
+            Expr::Block(eb) if eb.label.is_none() => {
+                let code = self.lower_block(&eb.block.stmts, next, live)?;
+                Ok(vec![parse_quote!({ #(#code)* })])
+            }
+            Expr::Loop(el) => self.lower_loop(el, next, live),
+            Expr::While(ew) => self.lower_while(ew, next, live),
+            Expr::ForLoop(ef) => Err(Error::new_spanned(
+                ef.for_token,
+                "state machines can't `.await` in `for` loops, use `while let` with an iterator stored in an annotated `let`",
+            )),
+            Expr::Block(eb) => Err(Error::new_spanned(
+                &eb.label,
+                "state machines can't `.await` in labeled blocks",
+            )),
+            expr => Err(unsupported_await(&expr_await(expr).unwrap())),
+        }
+    }
+
+    fn lower_if(&mut self, ei: &ExprIf, next: Next, live: &[Ident]) -> Result<Expr> {
+        check_no_await(&ei.cond)?;
+        let then_stmts = &ei.then_branch.stmts;
+        if let Expr::Let(el) = &*ei.cond {
+            self.check_bindings(&el.pat, then_stmts)?;
+        }
+
+        let cond = self.rewrite_expr(&ei.cond)?;
+        let then_code = self.lower_block(then_stmts, next, live)?;
+        let else_code = match ei.else_branch.as_ref().map(|(_, expr)| &**expr) {
+            Some(Expr::Block(eb)) if eb.label.is_none() => {
+                self.lower_block(&eb.block.stmts, next, live)?
+            }
+            Some(expr) => self.lower_block(&[Stmt::Expr(expr.clone())], next, live)?,
+            None => self.finish(next, None)?,
+        };
+
+        Ok(parse_quote!(if #cond { #(#then_code)* } else { #(#else_code)* }))
+    }
+
+    fn lower_match(&mut self, em: &ExprMatch, next: Next, live: &[Ident]) -> Result<Expr> {
+        check_no_await(&em.expr)?;
+
+        let mut em = em.clone();
+        em.expr = Box::new(self.rewrite_expr(&em.expr)?);
+        for arm in em.arms.iter_mut() {
+            if let Some((_, guard)) = &mut arm.guard {
+                check_no_await(guard)?;
+                **guard = self.rewrite_expr(guard)?;
+            }
+
+            let stmts = match &*arm.body {
+                Expr::Block(eb) if eb.label.is_none() => eb.block.stmts.clone(),
+                expr => vec![Stmt::Expr(expr.clone())],
+            };
+            self.check_bindings(&arm.pat, &stmts)?;
+            self.rewrite(|r| r.visit_pat_mut(&mut arm.pat))?;
+
+            let code = self.lower_block(&stmts, next, live)?;
+            arm.body = parse_quote!({ #(#code)* });
+            arm.comma = Some(Default::default());
+        }
+
+        Ok(Expr::Match(em))
+    }
+
+    fn lower_loop(&mut self, el: &ExprLoop, next: Next, live: &[Ident]) -> Result<Vec<Stmt>> {
+        if let Some(label) = &el.label {
+            return Err(Error::new_spanned(
+                label,
+                "state machines can't `.await` in labeled loops",
+            ));
+        }
+
+        let exit = self.exit_state(next, live)?;
+        let live: Vec<Ident> = idents(&el.body).into_iter().chain(live.to_vec()).collect();
+        let head = self.new_state(&live);
+
+        self.loops.push((exit, head));
+        let code = self.lower_block(&el.body.stmts, Next::Goto(head), &live);
+        self.loops.pop();
+        self.states[head].code = code?;
+
+        Ok(vec![Stmt::Expr(self.goto(head))])
+    }
+
+    fn lower_while(&mut self, ew: &ExprWhile, next: Next, live: &[Ident]) -> Result<Vec<Stmt>> {
+        if let Some(label) = &ew.label {
+            return Err(Error::new_spanned(
+                label,
+                "state machines can't `.await` in labeled loops",
+            ));
+        }
+        check_no_await(&ew.cond)?;
+        if let Expr::Let(el) = &*ew.cond {
+            self.check_bindings(&el.pat, &ew.body.stmts)?;
+        }
+
+        let exit = self.exit_state(next, live)?;
+        let live: Vec<Ident> = idents(ew).into_iter().chain(live.to_vec()).collect();
+        let head = self.new_state(&live);
+
+        self.loops.push((exit, head));
+        let cond = self.rewrite_expr(&ew.cond);
+        let code = self.lower_block(&ew.body.stmts, Next::Goto(head), &live);
+        self.loops.pop();
+        let (cond, code) = (cond?, code?);
+
+        let exit = self.goto(exit);
+        self.states[head].code = vec![parse_quote!(
+            if #cond {
+                #(#code)*
+            } else #exit
+        )];
+
+        Ok(vec![Stmt::Expr(self.goto(head))])
+    }
+
+    fn exit_state(&mut self, next: Next, live: &[Ident]) -> Result<usize> {
+        match next {
+            Next::Goto(state) => Ok(state),
+            Next::Return => {
+                let state = self.new_state(live);
+                self.states[state].code = self.finish(Next::Return, None)?;
+                Ok(state)
+            }
+        }
+    }
+
+    fn lower_let(&mut self, local: &Local, rest: &[Stmt]) -> Result<Stmt> {
+        let init = match &local.init {
+            Some((_, init)) => Some(self.rewrite_expr(init)?),
+            None => None,
+        };
+        self.bind(local, init, None, rest)
+    }
+
+    fn bind(
+        &mut self,
+        local: &Local,
+        init: Option<Expr>,
+        inferred: Option<Type>,
+        rest: &[Stmt],
+    ) -> Result<Stmt> {
+        let (pat, ty) = match &local.pat {
+            Pat::Type(pt) => (&*pt.pat, Some(&*pt.ty)),
+            pat => (pat, None),
+        };
+
+        match pat {
+            Pat::Ident(pi) if pi.by_ref.is_none() && pi.subpat.is_none() => {
+                self.shadow(&pi.ident)?;
+
+                if is_used_after_await(&pi.ident, rest) {
+                    let ty = ty.cloned().or(inferred).ok_or_else(|| {
+                        Error::new_spanned(
+                            pi,
+                            format!(
+                                "`{}` is used after `.await`, it needs a type annotation to be stored in the future",
+                                pi.ident
+                            ),
+                        )
+                    })?;
+                    if init.is_none() {
+                        return Err(Error::new_spanned(
+                            pi,
+                            format!(
+                                "`{}` is used after `.await`, it has to be initialized right away",
+                                pi.ident
+                            ),
+                        ));
+                    }
+                    check_stored_type(&pi.ident, &ty)?;
+                    self.store(pi, ty);
+                }
+            }
+            pat => {
+                for ident in bindings(pat) {
+                    self.shadow(&ident)?;
+                    if is_used_after_await(&ident, rest) {
+                        return Err(Error::new_spanned(
+                            &ident,
+                            format!(
+                                "`{}` is used after `.await`, only locals bound to a single variable can be stored in the future",
+                                ident
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut pat = local.pat.clone();
+        RenameSelf(self.item).visit_pat_mut(&mut pat);
+        let attrs = &local.attrs;
+        Ok(match init {
+            Some(init) => parse_quote!(#(#attrs)* let #pat = #init;),
+            None => parse_quote!(#(#attrs)* let #pat;),
+        })
+    }
+
+    fn check_bindings(&self, pat: &Pat, stmts: &[Stmt]) -> Result<()> {
+        for ident in bindings(pat) {
+            if self.scope.iter().any(|&l| self.locals[l].ident == ident) {
+                return Err(shadow_error(&ident));
+            }
+            if is_used_after_await(&ident, stmts) {
+                return Err(Error::new_spanned(
+                    &ident,
+                    format!(
+                        "`{}` is used after `.await`, pattern bindings can't be stored in the future, assign it to an annotated `let` first",
+                        ident
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn start_await(&mut self, call: &ExprMethodCall, live: &[Ident]) -> Result<(Vec<Stmt>, usize)> {
+        let method = self.awaited_method(call)?;
+
+        let idx = self.awaits;
+        self.awaits += 1;
+
+        let mut start = Vec::new();
+        let mut args = Vec::new();
+        let mut temporaries = Vec::new();
+        let take_args = takes_args(method);
+        for (i, (expr, arg)) in call
+            .args
+            .iter()
+            .zip(method.sig.inputs.iter().skip(1))
+            .enumerate()
+        {
+            let ty = match arg {
+                FnArg::Typed(pt) => &*pt.ty,
+                FnArg::Receiver(_) => unreachable!(),
+            };
+            check_no_await(expr)?;
+            let expr = self.rewrite_expr(expr)?;
+
+            if let Type::Reference(_) = ty {
+                // borrowed arguments are passed again every time the method is polled
+                if !is_place(&expr) {
+                    return Err(Error::new_spanned(
+                        &call.args[i],
+                        "state machines only support borrowing locals, `self` and constants in arguments of awaited methods",
+                    ));
+                }
+                args.push(expr);
+                continue;
+            }
+
+            if has_lifetimes(ty) {
+                return Err(Error::new_spanned(
+                    ty,
+                    "state machines can't store arguments with lifetimes",
+                ));
+            }
+            let field = self.add_field(format_ident!("__await{}_{}", idx, i), ty.clone());
+            let field = &self.fields[field].ident;
+            start.push(parse_quote!(__machine.#field = ::core::option::Option::Some(#expr);));
+            if take_args {
+                args.push(parse_quote!(&mut __machine.#field));
+            } else {
+                args.push(parse_quote!(__machine.#field.unwrap()));
+            }
+            temporaries.push(field.clone());
+        }
+
+        if let Some(ty) = state_type(method) {
+            let field = self.add_field(format_ident!("__await{}_state", idx), ty);
+            let field = &self.fields[field].ident;
+            args.push(parse_quote!(
+                __machine.#field.get_or_insert_with(::core::default::Default::default)
+            ));
+            temporaries.push(field.clone());
+        }
+
+        let state = self.new_state(live);
+        start.push(Stmt::Expr(self.goto(state)));
+
+        let receiver: Expr = match method.sig.inputs.first() {
+            Some(FnArg::Receiver(r)) if r.mutability.is_some() => parse_quote!(&mut *this),
+            _ => parse_quote!(&*this),
+        };
+        let path = trait_method_path(self.item, method, poll_method_ident(method));
+        let stores = self.stores(state);
+        let poll: Block = parse_quote!({
+            let __value = match #path(#receiver, #(#args,)* __cx) {
+                ::core::task::Poll::Ready(value) => value,
+                ::core::task::Poll::Pending => {
+                    #(#stores)*
+                    return ::core::task::Poll::Pending;
+                }
+            };
+            #(__machine.#temporaries = ::core::option::Option::None;)*
+        });
+        self.states[state].code = poll.stmts;
+
+        Ok((start, state))
+    }
+
+    fn awaited_method(&self, call: &ExprMethodCall) -> Result<&'a TraitItemMethod> {
+        let item = self.item;
+        let method = item
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                TraitItem::Method(m) => Some(m),
+                _ => None,
+            })
+            .find(|m| m.sig.ident == call.method)
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    &call.method,
+                    format!(
+                        "no async method `{}` in `{}`, state machines can only await the trait's own methods",
+                        call.method, self.item.ident
+                    ),
+                )
+            })?;
+
+        let error = |message| Err(Error::new_spanned(&call.method, message));
+        if method.sig.asyncness.is_none() {
+            return error("state machines can only await async methods");
+        }
+        if method.default.is_some() {
+            return error("state machines can't await provided methods");
+        }
+        if has_cancel_hook(method) {
+            return error("state machines can't await methods with a cancellation hook");
+        }
+        if method.sig.generics.type_params().next().is_some()
+            || method.sig.generics.const_params().next().is_some()
+            || method.sig.inputs.iter().any(|arg| match arg {
+                FnArg::Typed(pt) => matches!(&*pt.ty, Type::ImplTrait(_)),
+                FnArg::Receiver(_) => false,
+            })
+        {
+            return error("state machines can't await generic methods");
+        }
+        if call.turbofish.is_some() {
+            return error("state machines can't await generic methods");
+        }
+        match (method.sig.inputs.first(), self.method.sig.inputs.first()) {
+            (Some(FnArg::Receiver(r)), Some(FnArg::Receiver(own))) if r.reference.is_some() => {
+                if r.mutability.is_some() && own.mutability.is_none() {
+                    return error("the awaited method takes `&mut self`, but the state machine only has `&self`");
+                }
+            }
+            _ => {
+                return error(
+                    "state machines can only await methods with a `&self` or `&mut self` receiver",
+                )
+            }
+        }
+        if call.args.len() + 1 != method.sig.inputs.len() {
+            return error("wrong number of arguments");
+        }
+
+        Ok(method)
+    }
+
+    fn awaited_output(&self, call: &ExprMethodCall, is_try: bool) -> Option<Type> {
+        let method = self.awaited_method(call).ok()?;
+        let ty = match &method.sig.output {
+            ReturnType::Default => parse_quote!(()),
+            ReturnType::Type(_, ty) => *ty.clone(),
+        };
+        let ty = if is_try { success_type(&ty)? } else { ty };
+        if has_lifetimes(&ty) {
+            None
+        } else {
+            Some(ty)
+        }
+    }
+
+    fn await_value(&self, is_try: bool, expr: &Expr) -> Result<Expr> {
+        if !is_try {
+            return Ok(parse_quote!(__value));
+        }
+        let try_token = match expr {
+            Expr::Try(et) => et.question_token,
+            _ => unreachable!(),
+        };
+        desugar_try(self.method, parse_quote!(__value), try_token)
+    }
+
+    fn rewrite(&self, f: impl FnOnce(&mut Rewrite)) -> Result<()> {
+        let mut rewrite = Rewrite {
+            method: self.method,
+            stored: self
+                .scope
+                .iter()
+                .map(|&local| self.locals[local].ident.clone())
+                .collect(),
+            targets: self
+                .loops
+                .last()
+                .map(|&(exit, head)| (self.goto(exit), self.goto(head))),
+            nested_loop: false,
+            closure: false,
+            error: None,
+        };
+        f(&mut rewrite);
+        match rewrite.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn rewrite_expr(&self, expr: &Expr) -> Result<Expr> {
+        let mut expr = expr.clone();
+        self.rewrite(|r| r.visit_expr_mut(&mut expr))?;
+        RenameSelf(self.item).visit_expr_mut(&mut expr);
+        Ok(expr)
+    }
+
+    fn rewrite_stmt(&self, stmt: &Stmt) -> Result<Stmt> {
+        let mut stmt = stmt.clone();
+        self.rewrite(|r| r.visit_stmt_mut(&mut stmt))?;
+        RenameSelf(self.item).visit_stmt_mut(&mut stmt);
+        Ok(stmt)
+    }
+}
+
+fn ret(value: &Expr) -> Expr {
+    parse_quote!({
+        __machine.__state = ::core::primitive::usize::MAX;
+        return ::core::task::Poll::Ready(#value);
+    })
+}
+
+fn desugar_try(method: &TraitItemMethod, expr: Expr, try_token: Token![?]) -> Result<Expr> {
+    let output = match &method.sig.output {
+        ReturnType::Type(_, ty) => output_kind(ty),
+        ReturnType::Default => None,
+    };
+    match output {
+        Some(kind) if kind == "Result" => {
+            let ret = ret(&parse_quote!(::core::result::Result::Err(
+                ::core::convert::From::from(error)
+            )));
+            Ok(parse_quote!(match #expr {
+                ::core::result::Result::Ok(value) => value,
+                ::core::result::Result::Err(error) => #ret,
+            }))
+        }
+        Some(kind) if kind == "Option" => {
+            let ret = ret(&parse_quote!(::core::option::Option::None));
+            Ok(parse_quote!(match #expr {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => #ret,
+            }))
+        }
+        _ => Err(Error::new_spanned(
+            try_token,
+            "state machines only support `?` in methods returning `Result` or `Option`",
+        )),
+    }
+}
+
+fn output_kind(ty: &Type) -> Option<&Ident> {
+    match ty {
+        Type::Path(tp) => tp.path.segments.last().map(|segment| &segment.ident),
+        _ => None,
+    }
+}
+
+fn success_type(ty: &Type) -> Option<Type> {
+    let segment = match ty {
+        Type::Path(tp) => tp.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Result" && segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn check_stored_type(ident: &Ident, ty: &Type) -> Result<()> {
+    struct Elided(bool);
+
+    impl VisitMut for Elided {
+        fn visit_type_reference_mut(&mut self, i: &mut TypeReference) {
+            self.0 |= i.lifetime.is_none();
+            visit_type_reference_mut(self, i);
+        }
+
+        fn visit_lifetime_mut(&mut self, i: &mut Lifetime) {
+            self.0 |= i.ident == "_";
+        }
+    }
+
+    let mut elided = Elided(false);
+    elided.visit_type_mut(&mut ty.clone());
+    if elided.0 {
+        Err(Error::new_spanned(
+            ty,
+            format!(
+                "`{}` is used after `.await`, its type can't have elided lifetimes",
+                ident
+            ),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn has_lifetimes(ty: &Type) -> bool {
+    struct Lifetimes(bool);
+
+    impl VisitMut for Lifetimes {
+        fn visit_type_reference_mut(&mut self, _: &mut TypeReference) {
+            self.0 = true;
+        }
+
+        fn visit_lifetime_mut(&mut self, _: &mut Lifetime) {
+            self.0 = true;
+        }
+    }
+
+    let mut lifetimes = Lifetimes(false);
+    lifetimes.visit_type_mut(&mut ty.clone());
+    lifetimes.0
+}
+
+fn shadow_error(ident: &Ident) -> Error {
+    Error::new_spanned(
+        ident,
+        format!(
+            "`{}` shadows a local that's used after `.await`, state machines only support shadowing in the same block",
+            ident
+        ),
+    )
+}
+
+fn unsupported_await(await_expr: &ExprAwait) -> Error {
+    Error::new_spanned(
+        await_expr,
+        "unsupported `.await`, state machines only support `self.method(..).await` as a statement, in a `let` or as the last expression, and `.await`s in `if`, `match`, `loop` and `while`",
+    )
+}
+
+fn check_no_await(expr: &Expr) -> Result<()> {
+    match expr_await(expr) {
+        Some(await_expr) => Err(unsupported_await(&await_expr)),
+        None => Ok(()),
+    }
+}
+
+fn is_await_call(expr: &Expr) -> bool {
+    let expr = match expr {
+        Expr::Try(et) => &*et.expr,
+        expr => expr,
+    };
+    matches!(expr, Expr::Await(_))
+}
+
+fn await_call(expr: &Expr) -> Result<(&ExprMethodCall, bool)> {
+    let (expr, is_try) = match expr {
+        Expr::Try(et) => (&*et.expr, true),
+        expr => (expr, false),
+    };
+    let await_expr = match expr {
+        Expr::Await(ea) => ea,
+        expr => return Err(unsupported_await(&expr_await(expr).unwrap())),
+    };
+    match &*await_expr.base {
+        Expr::MethodCall(call) if matches!(&*call.receiver, Expr::Path(ep) if ep.path.is_ident("self")) =>
+        {
+            for arg in call.args.iter() {
+                check_no_await(arg)?;
+            }
+            Ok((call, is_try))
+        }
+        base => Err(Error::new_spanned(
+            base,
+            "state machines can only await the trait's methods called on `self`",
+        )),
+    }
+}
+
+fn is_place(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(_) | Expr::Lit(_) => true,
+        Expr::Reference(er) => is_place(&er.expr),
+        Expr::Field(ef) => is_place(&ef.base),
+        Expr::Index(ei) => is_place(&ei.expr) && expr_is_pure(&ei.index),
+        Expr::Paren(ep) => is_place(&ep.expr),
+        Expr::Unary(eu) => matches!(eu.op, syn::UnOp::Deref(_)) && is_place(&eu.expr),
+        _ => false,
+    }
+}
+
+// `is_none_or` needs Rust 1.82
+#[allow(clippy::unnecessary_map_or)]
+fn expr_is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(_) | Expr::Lit(_) => true,
+        Expr::Range(er) => {
+            er.from.as_deref().map_or(true, expr_is_pure)
+                && er.to.as_deref().map_or(true, expr_is_pure)
+        }
+        Expr::Field(ef) => expr_is_pure(&ef.base),
+        Expr::Paren(ep) => expr_is_pure(&ep.expr),
+        _ => false,
+    }
+}
+
+fn expr_await(expr: &Expr) -> Option<ExprAwait> {
+    let mut find = FindAwait(None);
+    find.visit_expr_mut(&mut expr.clone());
+    find.0
+}
+
+fn stmt_await(stmt: &Stmt) -> Option<ExprAwait> {
+    let mut find = FindAwait(None);
+    find.visit_stmt_mut(&mut stmt.clone());
+    find.0
+}
+
+struct FindAwait(Option<ExprAwait>);
+
+impl VisitMut for FindAwait {
+    fn visit_expr_mut(&mut self, i: &mut Expr) {
+        match i {
+            Expr::Await(ea) => {
+                if self.0.is_none() {
+                    self.0 = Some(ea.clone());
+                }
+            }
+            Expr::Closure(_) | Expr::Async(_) => {}
+            _ => visit_expr_mut(self, i),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _: &mut Item) {}
+}
+
+fn is_used_after_await(ident: &Ident, stmts: &[Stmt]) -> bool {
+    stmts
+        .iter()
+        .skip_while(|stmt| stmt_await(stmt).is_none())
+        .any(|stmt| idents(stmt).contains(ident))
+}
+
+fn idents(tokens: impl ToTokens) -> Vec<Ident> {
+    fn collect(tokens: TokenStream, idents: &mut Vec<Ident>) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => idents.push(ident),
+                TokenTree::Group(group) => collect(group.stream(), idents),
+                _ => {}
+            }
+        }
+    }
+
+    let mut idents = Vec::new();
+    collect(tokens.into_token_stream(), &mut idents);
+    idents
+}
+
+fn bindings(pat: &Pat) -> Vec<Ident> {
+    struct Bindings(Vec<Ident>);
+
+    impl VisitMut for Bindings {
+        fn visit_pat_ident_mut(&mut self, i: &mut PatIdent) {
+            if !i.ident.to_string().starts_with(char::is_uppercase) {
+                self.0.push(i.ident.clone());
+            }
+            visit_pat_ident_mut(self, i);
+        }
+    }
+
+    let mut bindings = Bindings(Vec::new());
+    bindings.visit_pat_mut(&mut pat.clone());
+    bindings.0
+}
+
+struct Rewrite<'a> {
+    method: &'a TraitItemMethod,
+    stored: Vec<Ident>,
+    targets: Option<(Expr, Expr)>,
+    nested_loop: bool,
+    closure: bool,
+    error: Option<Error>,
+}
+
+impl Rewrite<'_> {
+    fn fail(&mut self, error: Error) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl VisitMut for Rewrite<'_> {
+    fn visit_expr_mut(&mut self, i: &mut Expr) {
+        match i {
+            Expr::Closure(_) | Expr::Async(_) => {
+                let closure = mem::replace(&mut self.closure, true);
+                let nested_loop = mem::replace(&mut self.nested_loop, true);
+                visit_expr_mut(self, i);
+                self.closure = closure;
+                self.nested_loop = nested_loop;
+            }
+            Expr::Loop(_) | Expr::While(_) | Expr::ForLoop(_) => {
+                let nested_loop = mem::replace(&mut self.nested_loop, true);
+                visit_expr_mut(self, i);
+                self.nested_loop = nested_loop;
+            }
+            Expr::Return(er) if !self.closure => {
+                if let Some(expr) = &mut er.expr {
+                    self.visit_expr_mut(expr);
+                }
+                let value = er
+                    .expr
+                    .take()
+                    .map_or_else(|| parse_quote!(()), |expr| *expr);
+                *i = ret(&value);
+            }
+            Expr::Try(et) if !self.closure => {
+                self.visit_expr_mut(&mut et.expr);
+                match desugar_try(self.method, (*et.expr).clone(), et.question_token) {
+                    Ok(expr) => *i = expr,
+                    Err(e) => self.fail(e),
+                }
+            }
+            Expr::Break(eb) if !self.nested_loop && self.targets.is_some() => {
+                if eb.label.is_some() || eb.expr.is_some() {
+                    let error = Error::new_spanned(
+                        &*eb,
+                        "state machines don't support labels and values in `break` of loops with `.await`",
+                    );
+                    self.fail(error);
+                } else {
+                    *i = self.targets.as_ref().unwrap().0.clone();
+                }
+            }
+            Expr::Continue(ec) if !self.nested_loop && self.targets.is_some() => {
+                if ec.label.is_some() {
+                    let error = Error::new_spanned(
+                        &*ec,
+                        "state machines don't support labels in `continue` of loops with `.await`",
+                    );
+                    self.fail(error);
+                } else {
+                    *i = self.targets.as_ref().unwrap().1.clone();
+                }
+            }
+            _ => visit_expr_mut(self, i),
+        }
+    }
+
+    fn visit_pat_ident_mut(&mut self, i: &mut PatIdent) {
+        if self.stored.contains(&i.ident) {
+            self.fail(shadow_error(&i.ident));
+        }
+        visit_pat_ident_mut(self, i);
+    }
+
+    fn visit_ident_mut(&mut self, i: &mut Ident) {
+        if i == "self" {
+            *i = format_ident!("this", span = i.span());
+        }
+    }
+
+    fn visit_macro_mut(&mut self, i: &mut Macro) {
+        i.tokens = rename_self_tokens(mem::take(&mut i.tokens));
+        visit_macro_mut(self, i);
+    }
+
+    // nested items can't use the locals or `self`, except for statement macros
+    fn visit_item_mut(&mut self, i: &mut Item) {
+        if let Item::Macro(im) = i {
+            if im.ident.is_none() {
+                self.visit_macro_mut(&mut im.mac);
+            }
+        }
+    }
+}
+
+fn rename_self_tokens(tokens: TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) if ident == "self" => {
+                TokenTree::Ident(format_ident!("this", span = ident.span()))
+            }
+            TokenTree::Group(group) => {
+                let mut renamed = Group::new(group.delimiter(), rename_self_tokens(group.stream()));
+                renamed.set_span(group.span());
+                TokenTree::Group(renamed)
+            }
+            token => token,
+        })
+        .collect()
+}
@@ -2,12 +2,23 @@ use crate::{
     ext_trait_name,
     input::TraitInput,
     methods::{
-        CleanUpMutPatternsExpand, ExtMethodExpand, FutureAliasExpand, FutureStructExpand,
-        ImplFutureExpand, PollMethodExpand,
+        desugar_impl_trait, elide_lifetimes, elide_method, has_elided_impl_trait,
+        has_elided_output, is_gat_method, qualify_calls, with_owned_variant, CancelMethodExpand,
+        CleanUpMutPatternsExpand, DynMethodExpand, DynamicProvidedFnExpand, ExtMethodExpand,
+        FutureAliasExpand, FutureStructExpand, GatMethodExpand, GatTypeExpand, ImplDropExpand,
+        ImplFusedFutureExpand, ImplFutureExpand, ImplStateMachineFutureExpand,
+        ImplStateMachineUnpinExpand, ImplUnpinExpand, NativeMethodExpand, OverridableMethodExpand,
+        PollMethodExpand, StateMachineStructExpand, UnpinExtMethodExpand,
     },
 };
 use macro_compose::{Collector, Context, Expand};
-use syn::{parse_quote, ItemTrait, TraitItem};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+use syn::{parse_quote, ItemTrait, TraitItem, Visibility};
 
 pub struct PollTraitExpand;
 
@@ -20,18 +31,44 @@ impl Expand<ItemTrait> for PollTraitExpand {
         item.items = item
             .items
             .into_iter()
-            .filter_map(|item| {
+            .map(|item| match item {
+                // the context makes the elision of the return type ambiguous and the bounds of
+                // desugared `impl Trait` arguments can't elide lifetimes
+                TraitItem::Method(m) if has_elided_output(&m) || has_elided_impl_trait(&m) => {
+                    elide_lifetimes(TraitItem::Method(m))
+                }
+                item => item,
+            })
+            .map(desugar_impl_trait)
+            .map(|item| qualify_calls(input, item))
+            .flat_map(|item| {
                 if let TraitItem::Method(m) = item {
-                    let mut subcontext = Context::new(c, m);
-                    let method = subcontext.capture(&PollMethodExpand(input))?;
+                    // these mirror the signatures of the extension trait
+                    let mut subcontext = Context::new(c, elide_method(&m));
+                    let future = subcontext.capture(&GatTypeExpand(input));
+                    let gat = subcontext.capture(&GatMethodExpand(input));
+                    let provided = subcontext.capture(&OverridableMethodExpand(input));
 
-                    let mut subcontext = Context::new(c, method);
+                    let mut subcontext = Context::new(c, m);
+                    let poll = gat.or_else(|| subcontext.capture(&PollMethodExpand));
+                    let cancel = subcontext.capture(&CancelMethodExpand);
 
-                    Some(TraitItem::Method(
-                        subcontext.capture(&CleanUpMutPatternsExpand)?,
-                    ))
+                    let methods = poll
+                        .into_iter()
+                        .chain(cancel)
+                        .filter_map(|method| {
+                            let mut subcontext = Context::new(c, method);
+                            subcontext.capture(&CleanUpMutPatternsExpand)
+                        })
+                        .chain(provided)
+                        .map(TraitItem::Method);
+                    future
+                        .map(TraitItem::Type)
+                        .into_iter()
+                        .chain(methods)
+                        .collect()
                 } else {
-                    Some(item)
+                    vec![item]
                 }
             })
             .collect();
@@ -40,13 +77,24 @@ impl Expand<ItemTrait> for PollTraitExpand {
             if let TraitItem::Method(m) = item {
                 let mut subcontext = Context::new_by_ref(c, m);
 
-                if let Some(res) = subcontext.capture(&PollMethodExpand(input)) {
+                if let Some(res) = subcontext.capture(&PollMethodExpand) {
                     *m = res;
                 }
             }
         }
 
-        TraitInput::strip(&mut item.attrs);
+        // the futures of the extension trait hold `dyn Trait + Send + Sync`
+        let attrs = TraitInput::from(item.attrs.as_slice());
+        if attrs.dynamic.is_some() {
+            if attrs.send.is_some() {
+                item.supertraits.push(parse_quote!(::core::marker::Send));
+            }
+            if attrs.sync.is_some() {
+                item.supertraits.push(parse_quote!(::core::marker::Sync));
+            }
+        }
+
+        TraitInput::remove(&mut item.attrs);
 
         Some(item)
     }
@@ -62,7 +110,8 @@ impl Expand<ItemTrait> for ExtensionTraitExpand {
         item.ident = ext_trait_name(input);
 
         let ident = &input.ident;
-        item.supertraits.push(parse_quote!(#ident));
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+        item.supertraits.push(parse_quote!(#ident #ty_generics));
 
         let attrs = TraitInput::from(item.attrs.as_slice());
         if attrs.dynamic.is_none() {
@@ -72,27 +121,173 @@ impl Expand<ItemTrait> for ExtensionTraitExpand {
         item.items = item
             .items
             .into_iter()
-            .filter_map(|item| {
-                if let TraitItem::Method(m) = item {
-                    let mut subcontext = Context::new(c, m);
+            .flat_map(with_owned_variant)
+            .map(elide_lifetimes)
+            .map(desugar_impl_trait)
+            .map(|item| qualify_calls(input, item))
+            .flat_map(|item| {
+                match item {
+                    // the trait already has the method
+                    TraitItem::Method(m) if is_gat_method(input, &m) => vec![],
+                    TraitItem::Method(m) => {
+                        let mut subcontext = Context::new(c, m);
+
+                        subcontext.expand(&FutureStructExpand(input));
+                        subcontext.expand(&ImplFutureExpand(input));
+                        subcontext.expand(&ImplUnpinExpand(input));
+                        subcontext.expand(&ImplDropExpand(input));
+                        subcontext.expand(&ImplFusedFutureExpand(input));
+                        subcontext.expand(&FutureAliasExpand(input));
+                        subcontext.expand(&DynamicProvidedFnExpand(input));
+                        subcontext.expand(&StateMachineStructExpand(input));
+                        subcontext.expand(&ImplStateMachineFutureExpand(input));
+                        subcontext.expand(&ImplStateMachineUnpinExpand(input));
+
+                        let method = subcontext.capture(&ExtMethodExpand(input));
+                        let unpin = subcontext.capture(&UnpinExtMethodExpand(input));
+
+                        method
+                            .into_iter()
+                            .chain(unpin)
+                            .filter_map(|method| {
+                                let mut subcontext = Context::new(c, method);
+                                subcontext.capture(&CleanUpMutPatternsExpand)
+                            })
+                            .map(TraitItem::Method)
+                            .collect()
+                    }
+                    _ => vec![],
+                }
+            })
+            .collect();
 
-                    subcontext.expand(&FutureStructExpand(input));
-                    subcontext.expand(&ImplFutureExpand(input));
-                    subcontext.expand(&FutureAliasExpand(input));
+        TraitInput::remove(&mut item.attrs);
 
-                    let method = subcontext.capture(&ExtMethodExpand(input))?;
-                    let mut subcontext = Context::new(c, method);
+        Some(item)
+    }
+}
+
+pub struct NativeTraitExpand;
+
+impl Expand<ItemTrait> for NativeTraitExpand {
+    type Output = ItemTrait;
+
+    fn expand(&self, input: &ItemTrait, c: &mut Collector) -> Option<Self::Output> {
+        TraitInput::from(input.attrs.as_slice()).native?;
+        let ident = TraitInput::native(&input.attrs)?.ok()?;
+
+        let mut item = input.clone();
+        let comment = format!(" [`{}`] with native async methods", input.ident);
+        item.attrs = vec![
+            parse_quote!(#[doc = #comment]),
+            // generic callers can't require `Send` futures, which is inherent to native traits
+            parse_quote!(#[allow(async_fn_in_trait)]),
+        ];
+        item.ident = ident;
+
+        item.items = item
+            .items
+            .into_iter()
+            .flat_map(with_owned_variant)
+            .map(elide_lifetimes)
+            .map(desugar_impl_trait)
+            .filter_map(|item| match item {
+                TraitItem::Type(ty) => Some(TraitItem::Type(ty)),
+                TraitItem::Method(m) => {
+                    let mut subcontext = Context::new(c, m);
                     subcontext
-                        .capture(&CleanUpMutPatternsExpand)
+                        .capture(&NativeMethodExpand(input))
                         .map(TraitItem::Method)
-                } else {
-                    None
                 }
+                _ => None,
             })
             .collect();
 
-        TraitInput::strip(&mut item.attrs);
+        Some(item)
+    }
+}
+
+pub struct DynTraitExpand;
+
+impl Expand<ItemTrait> for DynTraitExpand {
+    type Output = ItemTrait;
+
+    fn expand(&self, input: &ItemTrait, c: &mut Collector) -> Option<Self::Output> {
+        TraitInput::from(input.attrs.as_slice()).dyn_trait?;
+        let ident = TraitInput::dyn_trait(&input.attrs)?.ok()?;
+
+        let mut item = input.clone();
+        let comment = format!(" object safe [`{}`] returning boxed futures", input.ident);
+        item.attrs = vec![parse_quote!(#[doc = #comment])];
+        item.ident = ident;
+
+        item.items = item
+            .items
+            .into_iter()
+            .flat_map(with_owned_variant)
+            .map(elide_lifetimes)
+            .map(desugar_impl_trait)
+            .filter_map(|item| match item {
+                TraitItem::Type(ty) => Some(TraitItem::Type(ty)),
+                TraitItem::Method(m) => {
+                    let mut subcontext = Context::new(c, m);
+                    subcontext
+                        .capture(&DynMethodExpand(input))
+                        .map(TraitItem::Method)
+                }
+                _ => None,
+            })
+            .collect();
 
         Some(item)
     }
 }
+
+/// the hidden macro passing the poll trait to `#[async_trait_ext::delegate(...)]`
+pub struct DelegateMacroExpand;
+
+impl Expand<ItemTrait> for DelegateMacroExpand {
+    type Output = TokenStream;
+
+    fn expand(&self, input: &ItemTrait, c: &mut Collector) -> Option<Self::Output> {
+        TraitInput::from(input.attrs.as_slice()).delegate?;
+
+        let mut ctx = Context::new_by_ref(c, input);
+        let mut poll_trait = ctx.capture(&PollTraitExpand)?;
+        poll_trait.attrs.clear();
+        // only the signatures are forwarded, bodies could contain `$`
+        for item in poll_trait.items.iter_mut() {
+            if let TraitItem::Method(m) = item {
+                if m.default.is_some() {
+                    m.default = Some(parse_quote!({}));
+                }
+            }
+        }
+
+        // exported macros share the root of the crate
+        let ident = &input.ident;
+        let mut hasher = DefaultHasher::new();
+        poll_trait.to_token_stream().to_string().hash(&mut hasher);
+        let name = format_ident!("__async_trait_ext_{}_{:x}", ident, hasher.finish());
+        let vis = &input.vis;
+        let export = match vis {
+            Visibility::Public(_) => Some(quote!(#[macro_export])),
+            _ => None,
+        };
+
+        Some(quote!(
+            #[doc(hidden)]
+            #[allow(unused_macros)]
+            #export
+            macro_rules! #name {
+                ($callback:ident { $($args:tt)* }) => {
+                    ::async_trait_ext::$callback! { { #poll_trait } $($args)* }
+                };
+            }
+
+            #[doc(hidden)]
+            #[allow(unused_imports)]
+            #vis use #name as #ident;
+        ))
+    }
+}
@@ -1,12 +1,153 @@
-use crate::input::TraitInput;
-use macro_compose::{Collector, Context, Lint};
-use syn::ItemTrait;
+use crate::{
+    input::TraitInput,
+    methods::{forwarded_receiver, PollMethodExpand},
+};
+use macro_compose::{Collector, Context, Expand, Lint};
+use syn::{Error, ItemTrait, TraitItem};
 
 pub struct AttributeLint;
 
 impl Lint<ItemTrait> for AttributeLint {
     fn lint(&self, input: &ItemTrait, c: &mut Collector) {
         let mut ctx = Context::new_by_ref(c, &input.attrs);
-        ctx.lint(TraitInput::lint());
+        if !ctx.lint(TraitInput::lint()) {
+            return;
+        }
+
+        let attrs = TraitInput::from(input.attrs.as_slice());
+        if attrs.gat.is_some() && attrs.dynamic.is_some() {
+            c.error(Error::new_spanned(
+                &input.ident,
+                "gat traits can't be dynamic, generic associated types aren't object safe",
+            ));
+        } else if attrs.gat.is_some() && attrs.fused.is_some() {
+            c.error(Error::new_spanned(
+                &input.ident,
+                "gat traits can't be fused, the implementors write the futures",
+            ));
+        }
+
+        if (attrs.send.is_some() || attrs.sync.is_some()) && attrs.dynamic.is_none() {
+            c.error(Error::new_spanned(
+                &input.ident,
+                "`send` and `sync` are options of `dynamic`, like `dynamic(send)`",
+            ));
+        }
+        if attrs.futures_core.is_some() && attrs.fused.is_none() {
+            c.error(Error::new_spanned(
+                &input.ident,
+                "`futures_core` is an option of `fused`, like `fused(futures_core)`",
+            ));
+        }
+
+        if let Some(Err(e)) = TraitInput::native(&input.attrs) {
+            c.error(e);
+        }
+        if let Some(Err(e)) = TraitInput::dyn_trait(&input.attrs) {
+            c.error(e);
+        }
+        if let Some(Err(e)) = TraitInput::forward(&input.attrs) {
+            c.error(e);
+        }
+        if let Some(Err(e)) = TraitInput::ext(&input.attrs) {
+            c.error(e);
+        }
+        if attrs.ext.as_deref() == Some(input.ident.to_string().as_str()) {
+            c.error(Error::new_spanned(
+                &input.ident,
+                "the extension trait needs a different name than the trait",
+            ));
+        }
+        if let Some(Err(e)) = TraitInput::future(&input.attrs) {
+            c.error(e);
+        }
+    }
+}
+
+pub struct AssocItemLint;
+
+impl Lint<ItemTrait> for AssocItemLint {
+    fn lint(&self, input: &ItemTrait, c: &mut Collector) {
+        let attrs = TraitInput::from(input.attrs.as_slice());
+        // the dyn trait has the same associated types
+        if attrs.dyn_trait.is_some() {
+            for item in input.items.iter() {
+                if let TraitItem::Type(item) = item {
+                    if !item.generics.params.is_empty() {
+                        c.error(Error::new_spanned(
+                            item,
+                            "traits with a dyn trait can't have generic associated types, they aren't object safe",
+                        ));
+                    }
+                }
+            }
+        }
+        if attrs.dynamic.is_none() {
+            return;
+        }
+
+        // both prevent the trait from being used as a trait object
+        for item in input.items.iter() {
+            match item {
+                TraitItem::Const(item) => c.error(Error::new_spanned(
+                    item,
+                    "dynamic traits can't have associated consts",
+                )),
+                TraitItem::Type(item) if !item.generics.params.is_empty() => c.error(
+                    Error::new_spanned(item, "dynamic traits can't have generic associated types"),
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+pub struct ForwardLint;
+
+impl Lint<ItemTrait> for ForwardLint {
+    fn lint(&self, input: &ItemTrait, c: &mut Collector) {
+        let forwards = match TraitInput::forward(&input.attrs) {
+            Some(Ok(forwards)) => forwards,
+            _ => return,
+        };
+
+        // provided methods keep their default when they can't be forwarded
+        let required = input.items.iter().filter_map(|item| match item {
+            TraitItem::Method(m) if m.default.is_none() => Some(m),
+            _ => None,
+        });
+        for m in required {
+            // the expansion reports its own errors
+            let method = if m.sig.asyncness.is_some() {
+                match PollMethodExpand.expand(m, &mut Collector::new()) {
+                    Some(method) => method,
+                    None => continue,
+                }
+            } else {
+                m.clone()
+            };
+
+            let receiver = match forwarded_receiver(&method, false) {
+                Some(receiver) => receiver,
+                None => {
+                    c.error(Error::new_spanned(
+                        &m.sig,
+                        "forwarded methods must take `&self`, `&mut self` or `self: Pin<&mut Self>` and can only use `Self` for associated types otherwise",
+                    ));
+                    continue;
+                }
+            };
+            for forward in forwards.iter() {
+                if !receiver.forwarded_through(*forward) {
+                    c.error(Error::new_spanned(
+                        &m.sig.inputs[0],
+                        format!(
+                            "methods taking {} can't be forwarded through `{}`",
+                            receiver, forward
+                        ),
+                    ));
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,84 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::task::{Context, Poll};
+
+#[async_trait_ext]
+pub trait Stream {
+    type Item;
+    const LIMIT: usize;
+
+    async fn next(&mut self) -> Option<Self::Item>;
+    async fn push(&mut self, item: Self::Item) -> bool
+    where
+        Self::Item: Copy;
+}
+
+struct Numbers(Vec<u32>);
+
+impl Stream for Numbers {
+    type Item = u32;
+    const LIMIT: usize = 2;
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<u32>> {
+        Poll::Ready(self.0.pop())
+    }
+
+    fn poll_push(&mut self, item: u32, _: &mut Context) -> Poll<bool> {
+        if self.0.len() < Self::LIMIT {
+            self.0.push(item);
+            Poll::Ready(true)
+        } else {
+            Poll::Ready(false)
+        }
+    }
+}
+
+#[test]
+fn test_assoc_types() {
+    let mut numbers = Numbers(vec![]);
+    assert!(block_on(numbers.push(1)));
+    assert!(block_on(numbers.push(2)));
+    assert!(!block_on(numbers.push(3)));
+    assert_eq!(block_on(numbers.next()), Some(2));
+    assert_eq!(block_on(numbers.next()), Some(1));
+    assert_eq!(block_on(numbers.next()), None);
+}
+
+pub mod dynamic {
+    use async_trait_ext::async_trait_ext;
+
+    #[async_trait_ext(dynamic)]
+    pub trait Stream {
+        type Item: Copy;
+        type Error;
+
+        async fn next(&mut self) -> Result<Option<Self::Item>, <Self as Stream>::Error>;
+        async fn push(&mut self, item: Self::Item);
+    }
+}
+
+impl dynamic::Stream for Numbers {
+    type Item = u32;
+    type Error = ();
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Result<Option<u32>, ()>> {
+        Poll::Ready(Ok(self.0.pop()))
+    }
+
+    fn poll_push(&mut self, item: u32, _: &mut Context) -> Poll<()> {
+        self.0.push(item);
+        Poll::Ready(())
+    }
+}
+
+#[test]
+fn test_assoc_types_dynamic() {
+    use dynamic::StreamExt;
+
+    let stream: &mut dyn StreamExt<Item = u32, Error = ()> = &mut Numbers(vec![]);
+    block_on(stream.push(1));
+    assert_eq!(block_on(stream.next()), Ok(Some(1)));
+    assert_eq!(block_on(stream.next()), Ok(None));
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+#[async_trait_ext]
+pub trait Read {
+    type Item: Send;
+
+    async fn read(&mut self) -> Option<Self::Item>;
+    async fn remaining(&self) -> usize;
+
+    #[async_fn(provided, boxed)]
+    async fn read_pair(&mut self) -> Option<(Self::Item, Self::Item)> {
+        Some((self.read().await?, self.read().await?))
+    }
+
+    #[async_fn(provided, boxed)]
+    async fn is_longer(&self, other: &[u8], extra: &usize) -> bool {
+        self.remaining().await > other.len() + *extra
+    }
+
+    #[async_fn(provided, boxed_local)]
+    async fn read_shared(&mut self, counter: Rc<usize>) -> (Option<Self::Item>, usize) {
+        (self.read().await, *counter)
+    }
+}
+
+#[async_trait_ext(dynamic)]
+pub trait Stream: Send {
+    type Item: Send;
+
+    async fn next(&mut self) -> Option<Self::Item>;
+
+    #[async_fn(provided, boxed)]
+    async fn skip_one(&mut self) -> Option<Self::Item> {
+        self.next().await?;
+        self.next().await
+    }
+}
+
+#[async_trait_ext]
+pub trait Source<T: Clone + Send> {
+    async fn get(&self) -> T;
+
+    #[async_fn(provided, boxed)]
+    async fn get_twice(&self) -> (T, T) {
+        let value = self.get().await;
+        (value.clone(), value)
+    }
+}
+
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    type Item = u8;
+
+    fn poll_read(&mut self, _: &mut Context) -> Poll<Option<u8>> {
+        Poll::Ready((!self.0.is_empty()).then(|| self.0.remove(0)))
+    }
+
+    fn poll_remaining(&self, _: &mut Context) -> Poll<usize> {
+        Poll::Ready(self.0.len())
+    }
+}
+
+impl Stream for Bytes {
+    type Item = u8;
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<u8>> {
+        Poll::Ready((!self.0.is_empty()).then(|| self.0.remove(0)))
+    }
+}
+
+impl Source<String> for Bytes {
+    fn poll_get(&self, _: &mut Context) -> Poll<String> {
+        Poll::Ready(String::from_utf8(self.0.clone()).unwrap())
+    }
+}
+
+#[test]
+fn test_boxed() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    let pair: ReadReadPair<'_, Bytes> = assert_send(bytes.read_pair());
+    assert_eq!(block_on(pair), Some((1, 2)));
+    assert_eq!(block_on(bytes.read_pair()), None);
+
+    let bytes = Bytes(vec![1, 2, 3]);
+    let longer = {
+        let other = vec![0];
+        let extra = 1;
+        block_on(assert_send(bytes.is_longer(&other, &extra)))
+    };
+    assert!(longer);
+    assert!(!block_on(bytes.is_longer(&[0, 0], &1)));
+
+    let mut bytes = Bytes(vec![4]);
+    assert_eq!(block_on(bytes.read_shared(Rc::new(7))), (Some(4), 7));
+
+    let bytes = Bytes(b"hi".to_vec());
+    assert_eq!(
+        block_on(assert_send(bytes.get_twice())),
+        ("hi".to_owned(), "hi".to_owned())
+    );
+}
+
+#[test]
+fn test_boxed_dynamic() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    let stream: &mut dyn StreamExt<Item = u8> = &mut bytes;
+    let next: StreamSkipOne<'_, u8> = stream.skip_one();
+    assert_eq!(block_on(next), Some(2));
+    assert_eq!(block_on(stream.skip_one()), None);
+
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    let stream: &mut dyn Stream<Item = u8> = &mut bytes;
+    assert_eq!(block_on(assert_send(stream.skip_one())), Some(2));
+}
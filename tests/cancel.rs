@@ -0,0 +1,89 @@
+use async_trait_ext::async_trait_ext;
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+#[async_trait_ext]
+pub trait Lock {
+    #[async_fn(on_cancel)]
+    async fn lock(&self, id: u32);
+}
+
+#[derive(Default)]
+struct WaitQueue {
+    waiters: Cell<u32>,
+    cancelled: Cell<Option<u32>>,
+}
+
+impl Lock for WaitQueue {
+    fn poll_lock(&self, _: u32, _: &mut Context) -> Poll<()> {
+        if self.waiters.get() == 0 {
+            self.waiters.set(1);
+            Poll::Pending
+        } else {
+            self.waiters.set(0);
+            Poll::Ready(())
+        }
+    }
+
+    fn cancel_lock(&self, id: u32) {
+        self.waiters.set(0);
+        self.cancelled.set(Some(id));
+    }
+}
+
+#[test]
+fn test_cancel() {
+    let queue = WaitQueue::default();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    // never polled
+    drop(queue.lock(1));
+    assert_eq!(queue.cancelled.get(), None);
+
+    // completed
+    {
+        let mut fut = pin!(queue.lock(2));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(fut.as_mut().poll(&mut cx).is_ready());
+    }
+    assert_eq!(queue.cancelled.get(), None);
+
+    // abandoned
+    {
+        let mut fut = pin!(queue.lock(3));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+    }
+    assert_eq!(queue.cancelled.get(), Some(3));
+    assert_eq!(queue.waiters.get(), 0);
+}
+
+#[test]
+fn test_cancel_default() {
+    #[async_trait_ext(dynamic)]
+    trait Lock {
+        #[async_fn(on_cancel, state = "bool")]
+        async fn lock(&self);
+    }
+
+    struct Mutex;
+
+    impl Lock for Mutex {
+        fn poll_lock(&self, polled: &mut bool, _: &mut Context) -> Poll<()> {
+            if *polled {
+                Poll::Ready(())
+            } else {
+                *polled = true;
+                Poll::Pending
+            }
+        }
+    }
+
+    let lock: &dyn LockExt = &Mutex;
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut fut = pin!(lock.lock());
+    assert!(fut.as_mut().poll(&mut cx).is_pending());
+}
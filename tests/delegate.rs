@@ -0,0 +1,174 @@
+mod common;
+
+use async_trait_ext::{async_trait_ext, delegate};
+use common::block_on;
+use std::{
+    future::{ready, Future},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub mod transport {
+    use super::*;
+
+    #[async_trait_ext(rpitit, delegate)]
+    pub trait Read {
+        type Error;
+
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+        #[async_fn(on_cancel)]
+        async fn close(&mut self);
+        fn name(&self) -> &str;
+
+        #[async_fn(provided, overridable)]
+        async fn read_u8(&mut self) -> Option<u8> {
+            let mut buf = [0];
+            match self.read(&mut buf).await {
+                Ok(1) => Some(buf[0]),
+                _ => None,
+            }
+        }
+    }
+}
+
+use transport::{Read, ReadExt};
+
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    type Error = ();
+
+    fn poll_read(&mut self, buf: &mut [u8], _: &mut Context) -> Poll<Result<usize, ()>> {
+        let len = buf.len().min(self.0.len());
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0.drain(..len);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_close(&mut self, _: &mut Context) -> Poll<()> {
+        self.0.clear();
+        Poll::Ready(())
+    }
+
+    fn name(&self) -> &str {
+        "bytes"
+    }
+
+    fn provided_read_u8(&mut self) -> impl Future<Output = Option<u8>> {
+        // reads from the back to tell the override apart
+        ready(self.0.pop())
+    }
+}
+
+#[delegate(transport::Read, to = inner)]
+struct Metered<T> {
+    inner: T,
+    #[allow(dead_code)]
+    reads: usize,
+}
+
+#[delegate(Read)]
+struct Wrapper(Bytes);
+
+#[test]
+fn test_delegate() {
+    let mut metered = Metered {
+        inner: Bytes(vec![1, 2, 3]),
+        reads: 0,
+    };
+    let mut buf = [0; 2];
+    assert_eq!(block_on(metered.read(&mut buf)), Ok(2));
+    assert_eq!(buf, [1, 2]);
+    assert_eq!(metered.name(), "bytes");
+    block_on(metered.close());
+    assert!(metered.inner.0.is_empty());
+
+    let mut wrapper = Wrapper(Bytes(vec![1, 2, 3]));
+    assert_eq!(block_on(wrapper.read_u8()), Some(3));
+    assert_eq!(block_on(wrapper.read(&mut buf)), Ok(2));
+}
+
+#[async_trait_ext(delegate)]
+pub trait Source<T: Copy> {
+    async fn next(&mut self) -> Option<T>;
+}
+
+struct Repeat<T>(T);
+
+impl<T: Copy> Source<T> for Repeat<T> {
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<T>> {
+        Poll::Ready(Some(self.0))
+    }
+}
+
+#[delegate(Source)]
+struct Buffered<T>(Repeat<T>);
+
+#[test]
+fn test_generic() {
+    let mut source = Buffered(Repeat(1u8));
+    assert_eq!(block_on(source.next()), Some(1));
+}
+
+#[async_trait_ext(delegate)]
+pub trait AsyncWrite {
+    async fn write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8]) -> usize;
+}
+
+struct Sink;
+
+impl AsyncWrite for Sink {
+    fn poll_write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8], _: &mut Context) -> Poll<usize> {
+        Poll::Ready(buf.len())
+    }
+}
+
+#[delegate(AsyncWrite)]
+struct Counted {
+    sink: Sink,
+}
+
+#[test]
+fn test_pinned() {
+    let mut counted = Counted { sink: Sink };
+    assert_eq!(block_on(counted.write(&[1, 2])), 2);
+}
+
+pub mod a {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Token(pub u8);
+
+    #[async_trait_ext(delegate)]
+    pub trait Issuer {
+        async fn issue(&mut self) -> Token;
+    }
+}
+
+pub mod b {
+    use super::*;
+    // the signatures are pasted into this module, the types they name have to be in scope
+    use super::a::{Issuer, Token};
+
+    pub struct Counter(pub u8);
+
+    impl Issuer for Counter {
+        fn poll_issue(&mut self, _: &mut Context) -> Poll<Token> {
+            self.0 += 1;
+            Poll::Ready(Token(self.0))
+        }
+    }
+
+    #[delegate(Issuer)]
+    pub struct Shared(pub Counter);
+}
+
+#[test]
+fn test_other_module() {
+    use a::{IssuerExt, Token};
+
+    let mut shared = b::Shared(b::Counter(0));
+    assert_eq!(block_on(shared.issue()), Token(1));
+    assert_eq!(block_on(shared.issue()), Token(2));
+}
@@ -0,0 +1,223 @@
+mod common;
+
+use async_trait_ext::{async_trait_ext, dispatch};
+use common::block_on;
+use std::{
+    future::{ready, Future},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub mod transport {
+    use super::*;
+
+    #[async_trait_ext(rpitit, delegate)]
+    pub trait Read {
+        type Error;
+
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+        #[async_fn(on_cancel)]
+        async fn close(&mut self);
+        fn name(&self) -> &str;
+
+        #[async_fn(provided, overridable)]
+        async fn read_u8(&mut self) -> Option<u8> {
+            let mut buf = [0];
+            match self.read(&mut buf).await {
+                Ok(1) => Some(buf[0]),
+                _ => None,
+            }
+        }
+    }
+}
+
+use transport::{Read, ReadExt};
+
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    type Error = ();
+
+    fn poll_read(&mut self, buf: &mut [u8], _: &mut Context) -> Poll<Result<usize, ()>> {
+        let len = buf.len().min(self.0.len());
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0.drain(..len);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_close(&mut self, _: &mut Context) -> Poll<()> {
+        self.0.clear();
+        Poll::Ready(())
+    }
+
+    fn name(&self) -> &str {
+        "bytes"
+    }
+
+    fn provided_read_u8(&mut self) -> impl Future<Output = Option<u8>> {
+        // reads from the back to tell the override apart
+        ready(self.0.pop())
+    }
+}
+
+struct Closed;
+
+impl Read for Closed {
+    type Error = ();
+
+    fn poll_read(&mut self, _: &mut [u8], _: &mut Context) -> Poll<Result<usize, ()>> {
+        Poll::Ready(Err(()))
+    }
+
+    fn poll_close(&mut self, _: &mut Context) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    fn name(&self) -> &str {
+        "closed"
+    }
+}
+
+#[dispatch(transport::Read)]
+enum Transport {
+    Bytes(Bytes),
+    Closed { closed: Closed },
+}
+
+#[test]
+fn test_dispatch() {
+    let mut transport = Transport::Bytes(Bytes(vec![1, 2, 3, 4]));
+    let mut buf = [0; 2];
+    assert_eq!(block_on(transport.read(&mut buf)), Ok(2));
+    assert_eq!(buf, [1, 2]);
+    assert_eq!(transport.name(), "bytes");
+    // the futures of the variants have different types, the provided body is used
+    assert_eq!(block_on(transport.read_u8()), Some(3));
+    block_on(transport.close());
+
+    let mut transport = Transport::Closed { closed: Closed };
+    assert_eq!(block_on(transport.read(&mut buf)), Err(()));
+    assert_eq!(transport.name(), "closed");
+    assert_eq!(block_on(transport.read_u8()), None);
+}
+
+#[async_trait_ext(delegate)]
+pub trait Source<T: Copy> {
+    async fn next(&mut self) -> Option<T>;
+}
+
+struct Repeat<T>(T);
+
+impl<T: Copy> Source<T> for Repeat<T> {
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<T>> {
+        Poll::Ready(Some(self.0))
+    }
+}
+
+struct Empty;
+
+impl<T: Copy> Source<T> for Empty {
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<T>> {
+        Poll::Ready(None)
+    }
+}
+
+#[dispatch(Source)]
+enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+#[test]
+fn test_generic() {
+    let mut source: Either<Repeat<u8>, Empty> = Either::Left(Repeat(1));
+    assert_eq!(block_on(source.next()), Some(1));
+    let mut source: Either<Repeat<u8>, Empty> = Either::Right(Empty);
+    assert_eq!(block_on(source.next()), None);
+}
+
+#[async_trait_ext(delegate)]
+pub trait AsyncWrite {
+    async fn write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8]) -> usize;
+}
+
+struct Sink;
+
+impl AsyncWrite for Sink {
+    fn poll_write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8], _: &mut Context) -> Poll<usize> {
+        Poll::Ready(buf.len())
+    }
+}
+
+struct Full;
+
+impl AsyncWrite for Full {
+    fn poll_write<'a>(self: Pin<&'a mut Self>, _: &'a [u8], _: &mut Context) -> Poll<usize> {
+        Poll::Ready(0)
+    }
+}
+
+#[dispatch(AsyncWrite)]
+enum Output {
+    Sink(Sink),
+    Full(Full),
+}
+
+#[test]
+fn test_pinned() {
+    let mut output = Output::Sink(Sink);
+    assert_eq!(block_on(output.write(&[1, 2])), 2);
+    let mut output = Output::Full(Full);
+    assert_eq!(block_on(output.write(&[1, 2])), 0);
+}
+
+pub mod a {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Token(pub u8);
+
+    #[async_trait_ext(delegate)]
+    pub trait Issuer {
+        async fn issue(&mut self) -> Token;
+    }
+}
+
+pub mod b {
+    use super::*;
+    // the signatures are pasted into this module, the types they name have to be in scope
+    use super::a::{Issuer, Token};
+
+    pub struct Counter(pub u8);
+
+    impl Issuer for Counter {
+        fn poll_issue(&mut self, _: &mut Context) -> Poll<Token> {
+            self.0 += 1;
+            Poll::Ready(Token(self.0))
+        }
+    }
+
+    pub struct Fixed;
+
+    impl Issuer for Fixed {
+        fn poll_issue(&mut self, _: &mut Context) -> Poll<Token> {
+            Poll::Ready(Token(0))
+        }
+    }
+
+    #[dispatch(Issuer)]
+    pub enum Any {
+        Counter(Counter),
+        Fixed(Fixed),
+    }
+}
+
+#[test]
+fn test_other_module() {
+    use a::{IssuerExt, Token};
+
+    let mut issuer = b::Any::Counter(b::Counter(0));
+    assert_eq!(block_on(issuer.issue()), Token(1));
+    let mut issuer = b::Any::Fixed(b::Fixed);
+    assert_eq!(block_on(issuer.issue()), Token(0));
+}
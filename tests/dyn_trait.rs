@@ -0,0 +1,137 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::task::{Context, Poll};
+
+#[async_trait_ext(rpitit, dyn_trait = "DynRead")]
+pub trait Read {
+    type Error;
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+    #[async_fn(take_args)]
+    async fn skip_while(&mut self, pred: impl Fn(u8) -> bool) -> usize;
+    async fn for_each(&mut self, f: &mut impl FnMut(u8));
+    async fn starts_with(&self, prefix: impl AsRef<[u8]> + Copy) -> bool;
+    #[async_fn(take_args)]
+    async fn into_len(self) -> usize;
+
+    #[async_fn(provided)]
+    async fn read_u8(&mut self) -> Option<u8> {
+        let mut buf = [0];
+        match self.read(&mut buf).await {
+            Ok(1) => Some(buf[0]),
+            _ => None,
+        }
+    }
+}
+
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    type Error = ();
+
+    fn poll_read(&mut self, buf: &mut [u8], _: &mut Context) -> Poll<Result<usize, ()>> {
+        let len = buf.len().min(self.0.len());
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0.drain(..len);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_skip_while<F: Fn(u8) -> bool>(
+        &mut self,
+        pred: &mut Option<F>,
+        _: &mut Context,
+    ) -> Poll<usize> {
+        let pred = pred.take().unwrap();
+        let len = self.0.iter().take_while(|&&b| pred(b)).count();
+        self.0.drain(..len);
+        Poll::Ready(len)
+    }
+
+    fn poll_for_each<F: FnMut(u8)>(&mut self, f: &mut F, _: &mut Context) -> Poll<()> {
+        self.0.drain(..).for_each(f);
+        Poll::Ready(())
+    }
+
+    fn poll_starts_with<P: AsRef<[u8]> + Copy>(&self, prefix: P, _: &mut Context) -> Poll<bool> {
+        Poll::Ready(self.0.starts_with(prefix.as_ref()))
+    }
+
+    fn poll_into_len(this: &mut Option<Self>, _: &mut Context) -> Poll<usize> {
+        Poll::Ready(this.take().unwrap().0.len())
+    }
+}
+
+/// always fails to read
+struct Broken;
+
+impl Read for Broken {
+    type Error = ();
+
+    fn poll_read(&mut self, _: &mut [u8], _: &mut Context) -> Poll<Result<usize, ()>> {
+        Poll::Ready(Err(()))
+    }
+
+    fn poll_skip_while<F: Fn(u8) -> bool>(
+        &mut self,
+        _: &mut Option<F>,
+        _: &mut Context,
+    ) -> Poll<usize> {
+        Poll::Ready(0)
+    }
+
+    fn poll_for_each<F: FnMut(u8)>(&mut self, _: &mut F, _: &mut Context) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    fn poll_starts_with<P: AsRef<[u8]> + Copy>(&self, prefix: P, _: &mut Context) -> Poll<bool> {
+        Poll::Ready(prefix.as_ref().is_empty())
+    }
+
+    fn poll_into_len(_: &mut Option<Self>, _: &mut Context) -> Poll<usize> {
+        Poll::Ready(0)
+    }
+}
+
+fn readers() -> Vec<Box<dyn DynRead<Error = ()>>> {
+    vec![Box::new(Bytes(vec![0, 0, 1, 2])), Box::new(Broken)]
+}
+
+#[test]
+fn test_heterogeneous() {
+    let lens: Vec<_> = readers()
+        .into_iter()
+        .map(|reader| block_on(reader.into_len()))
+        .collect();
+    assert_eq!(lens, [4, 0]);
+
+    let bytes: Vec<_> = readers()
+        .iter_mut()
+        .map(|reader| block_on(reader.read_u8()))
+        .collect();
+    assert_eq!(bytes, [Some(0), None]);
+}
+
+#[test]
+fn test_erased_args() {
+    let mut readers = readers();
+    let skipped: Vec<_> = readers
+        .iter_mut()
+        .map(|reader| block_on(reader.skip_while(Box::new(|b| b == 0))))
+        .collect();
+    assert_eq!(skipped, [2, 0]);
+
+    let mut sum = 0;
+    for reader in readers.iter_mut() {
+        block_on(reader.for_each(&mut |b| sum += b));
+    }
+    assert_eq!(sum, 3);
+}
+
+#[test]
+fn test_sized_methods() {
+    let bytes = Bytes(vec![1, 2]);
+    assert!(block_on(DynRead::starts_with(&bytes, [1])));
+    assert!(!block_on(DynRead::starts_with(&Broken, [1])));
+}
@@ -1,4 +1,7 @@
+mod common;
+
 use async_trait_ext::async_trait_ext;
+use common::block_on;
 use std::task::{Context, Poll};
 
 #[test]
@@ -32,3 +35,83 @@ pub trait Foo {
     async fn method5<'a>(&'a self, a: &'a [u8]);
     async fn method6<'a>(&'a mut self, a: &'a [u8]);
 }
+
+#[async_trait_ext(dynamic)]
+pub trait Counter {
+    type Count;
+
+    async fn increment(&mut self) -> Self::Count;
+    async fn get(&self) -> Self::Count;
+}
+
+struct Count(u32);
+
+impl Counter for Count {
+    type Count = u32;
+
+    fn poll_increment(&mut self, _: &mut Context) -> Poll<u32> {
+        self.0 += 1;
+        Poll::Ready(self.0)
+    }
+
+    fn poll_get(&self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0)
+    }
+}
+
+#[test]
+fn test_trait_objects() {
+    let counter: &mut dyn Counter<Count = u32> = &mut Count(0);
+    assert_eq!(block_on(counter.increment()), 1);
+    assert_eq!(block_on(counter.get()), 1);
+
+    let mut counter: Box<dyn Counter<Count = u32> + Send> = Box::new(Count(1));
+    assert_eq!(block_on(counter.increment()), 2);
+
+    let counter: &(dyn Counter<Count = u32> + Send + Sync) = &Count(3);
+    assert_eq!(block_on(counter.get()), 3);
+}
+
+#[async_trait_ext(dynamic(send, sync))]
+pub trait Shared {
+    async fn get(&self) -> u32;
+    async fn set(&mut self, value: u32);
+
+    #[async_fn(provided, state_machine)]
+    async fn swap(&mut self, value: u32) -> u32 {
+        let old = self.get().await;
+        self.set(value).await;
+        old
+    }
+}
+
+struct Value(u32);
+
+impl Shared for Value {
+    fn poll_get(&self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0)
+    }
+
+    fn poll_set(&mut self, value: u32, _: &mut Context) -> Poll<()> {
+        self.0 = value;
+        Poll::Ready(())
+    }
+}
+
+fn assert_send<T: Send>(t: T) -> T {
+    t
+}
+
+#[test]
+fn test_send_sync() {
+    let mut value = Value(1);
+    assert_eq!(block_on(assert_send(value.swap(2))), 1);
+    assert_eq!(block_on(assert_send(value.get())), 2);
+
+    let shared: &mut dyn Shared = &mut value;
+    assert_eq!(block_on(assert_send(shared.swap(3))), 2);
+    assert_eq!(block_on(assert_send(shared.get())), 3);
+
+    let shared: Box<dyn Shared + Send + Sync> = Box::new(Value(4));
+    assert_eq!(block_on(assert_send(shared.get())), 4);
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    cell::Cell,
+    future::{ready, Future},
+    marker::PhantomPinned,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+#[async_trait_ext(forward(&mut, Box))]
+pub trait Counter {
+    type Count;
+    const STEP: u32;
+
+    async fn increment(&mut self) -> Self::Count;
+    async fn get(&self) -> Self::Count;
+    async fn add<T: Into<u32> + Copy>(&mut self, value: T);
+}
+
+struct Count(u32);
+
+impl Counter for Count {
+    type Count = u32;
+    const STEP: u32 = 2;
+
+    fn poll_increment(&mut self, _: &mut Context) -> Poll<u32> {
+        self.0 += Self::STEP;
+        Poll::Ready(self.0)
+    }
+
+    fn poll_get(&self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0)
+    }
+
+    fn poll_add<T: Into<u32> + Copy>(&mut self, value: T, _: &mut Context) -> Poll<()> {
+        self.0 += value.into();
+        Poll::Ready(())
+    }
+}
+
+fn increment_twice<C: Counter<Count = u32>>(mut counter: C) -> u32 {
+    block_on(counter.increment());
+    block_on(counter.add(1u8));
+    block_on(counter.increment());
+    block_on(counter.get())
+}
+
+#[test]
+fn test_unique() {
+    let mut count = Count(0);
+    assert_eq!(increment_twice(&mut count), 5);
+    assert_eq!(count.0, 5);
+    assert_eq!(increment_twice(Box::new(Count(1))), 6);
+    assert_eq!(<&mut Count as Counter>::STEP, 2);
+}
+
+#[async_trait_ext(forward(&mut, &, Box, Rc, Arc, Pin))]
+pub trait Clock {
+    async fn now(&self) -> u64;
+}
+
+struct Fixed(u64);
+
+impl Clock for Fixed {
+    fn poll_now(&self, _: &mut Context) -> Poll<u64> {
+        Poll::Ready(self.0)
+    }
+}
+
+fn now(clock: impl Clock) -> u64 {
+    block_on(clock.now())
+}
+
+#[test]
+fn test_shared() {
+    let mut fixed = Fixed(1);
+    assert_eq!(now(&fixed), 1);
+    fixed.0 = 2;
+    assert_eq!(now(&mut fixed), 2);
+    assert_eq!(now(Box::new(Fixed(3))), 3);
+    assert_eq!(now(Rc::new(Fixed(4))), 4);
+    assert_eq!(now(Arc::new(Fixed(5))), 5);
+    assert_eq!(now(Box::pin(Fixed(6))), 6);
+    assert_eq!(now(Pin::new(&Fixed(7))), 7);
+}
+
+#[async_trait_ext(forward(&mut, Box, Pin))]
+pub trait AsyncWrite {
+    async fn write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8]) -> usize;
+    async fn written(&self) -> usize;
+}
+
+struct Sink {
+    written: usize,
+    _pin: PhantomPinned,
+}
+
+impl AsyncWrite for Sink {
+    fn poll_write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8], _: &mut Context) -> Poll<usize> {
+        // SAFETY: `written` isn't structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        this.written += buf.len();
+        Poll::Ready(buf.len())
+    }
+
+    fn poll_written(&self, _: &mut Context) -> Poll<usize> {
+        Poll::Ready(self.written)
+    }
+}
+
+#[test]
+fn test_pinned() {
+    let mut sink = Box::pin(Sink {
+        written: 0,
+        _pin: PhantomPinned,
+    });
+    assert_eq!(block_on(sink.write(&[1, 2])), 2);
+    assert_eq!(block_on(Pin::new(&mut sink).write_pinned(&[3])), 1);
+    assert_eq!(block_on(sink.written()), 3);
+
+    let mut sink = Box::new(Box::pin(Sink {
+        written: 0,
+        _pin: PhantomPinned,
+    }));
+    assert_eq!(block_on(sink.write(&[1, 2, 3])), 3);
+}
+
+#[async_trait_ext(rpitit, forward(&mut))]
+pub trait Read {
+    async fn read(&mut self) -> Option<u8>;
+
+    #[async_fn(provided, overridable)]
+    async fn skip(&mut self) -> bool {
+        self.read().await.is_some()
+    }
+}
+
+struct Bytes {
+    bytes: Vec<u8>,
+    skipped: Rc<Cell<usize>>,
+}
+
+impl Read for Bytes {
+    fn poll_read(&mut self, _: &mut Context) -> Poll<Option<u8>> {
+        Poll::Ready(self.bytes.pop())
+    }
+
+    fn provided_skip(&mut self) -> impl Future<Output = bool> {
+        self.skipped.set(self.skipped.get() + 1);
+        ready(self.bytes.pop().is_some())
+    }
+}
+
+fn skip_all(mut reader: impl Read) -> usize {
+    let mut skipped = 0;
+    while block_on(reader.skip()) {
+        skipped += 1;
+    }
+    skipped
+}
+
+#[test]
+fn test_overridden() {
+    let skipped = Rc::new(Cell::new(0));
+    let bytes = |bytes| Bytes {
+        bytes,
+        skipped: skipped.clone(),
+    };
+
+    assert_eq!(skip_all(bytes(vec![1, 2])), 2);
+    assert_eq!(skipped.get(), 3);
+
+    // overridden methods require `Self: Sized`, pointers use the provided body
+    assert_eq!(skip_all(&mut bytes(vec![1, 2])), 2);
+    assert_eq!(skipped.get(), 3);
+}
+
+#[async_trait_ext(gat, forward(&mut, Box))]
+pub trait Source {
+    async fn next<'a>(&'a mut self) -> u32;
+}
+
+struct Numbers(u32);
+
+impl Source for Numbers {
+    type NextFuture<'a> = std::future::Ready<u32>;
+
+    fn next<'a>(&'a mut self) -> Self::NextFuture<'a> {
+        self.0 += 1;
+        ready(self.0)
+    }
+}
+
+#[test]
+fn test_gat() {
+    let mut numbers = Box::new(Numbers(0));
+    assert_eq!(block_on(<Box<Numbers> as Source>::next(&mut numbers)), 1);
+    let mut numbers = &mut numbers;
+    assert_eq!(
+        block_on(<&mut Box<Numbers> as Source>::next(&mut numbers)),
+        2
+    );
+}
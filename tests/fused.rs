@@ -0,0 +1,88 @@
+use async_trait_ext::async_trait_ext;
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+#[async_trait_ext(fused(futures_core))]
+pub trait Counter {
+    async fn next(&self) -> u32;
+}
+
+#[derive(Default)]
+struct Count(Cell<u32>);
+
+impl Counter for Count {
+    fn poll_next(&self, _: &mut Context) -> Poll<u32> {
+        let count = self.0.get() + 1;
+        self.0.set(count);
+        if count.is_multiple_of(2) {
+            Poll::Ready(count)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "`CounterNext` polled after completion")]
+fn test_fused() {
+    let count = Count::default();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let mut fut = pin!(count.next());
+    assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+    assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(2));
+    let _ = fut.as_mut().poll(&mut cx);
+}
+
+#[test]
+fn test_fused_future() {
+    use futures_core::future::FusedFuture;
+
+    let count = Count::default();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let mut fut = pin!(count.next());
+    assert!(!fut.is_terminated());
+    assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+    assert!(!fut.is_terminated());
+    assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(2));
+    assert!(fut.is_terminated());
+}
+
+#[test]
+fn test_fused_cancel() {
+    #[async_trait_ext(fused)]
+    trait Lock {
+        #[async_fn(on_cancel, state = "bool")]
+        async fn lock(&self);
+    }
+
+    struct Mutex(Cell<bool>);
+
+    impl Lock for Mutex {
+        fn poll_lock(&self, polled: &mut bool, _: &mut Context) -> Poll<()> {
+            if std::mem::replace(polled, true) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn cancel_lock(&self, _: &mut bool) {
+            self.0.set(true);
+        }
+    }
+
+    let mutex = Mutex(Cell::new(false));
+    let mut cx = Context::from_waker(Waker::noop());
+    {
+        let mut fut = pin!(mutex.lock());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(fut.as_mut().poll(&mut cx).is_ready());
+    }
+    assert!(!mutex.0.get());
+}
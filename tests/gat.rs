@@ -0,0 +1,122 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    fmt::Debug,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// pending on the first poll
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            Poll::Pending
+        }
+    }
+}
+
+#[async_trait_ext(gat, rpitit)]
+pub trait Read {
+    async fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> usize;
+
+    #[async_fn(provided)]
+    async fn read_u8(&mut self) -> Option<u8> {
+        let mut buf = [0];
+        match self.read(&mut buf).await {
+            0 => None,
+            _ => Some(buf[0]),
+        }
+    }
+}
+
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    type ReadFuture<'a> = Pin<Box<dyn Future<Output = usize> + 'a>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::ReadFuture<'a> {
+        Box::pin(async move {
+            YieldNow(false).await;
+            let len = buf.len().min(self.0.len());
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0.drain(..len);
+            len
+        })
+    }
+}
+
+#[async_trait_ext(gat)]
+pub trait Describe<P> {
+    async fn name(&self) -> String;
+    async fn describe<T: Debug>(&self, value: T) -> String;
+    async fn prefixed<'a, T: AsRef<str>>(&'a self, value: &'a T, prefix: P) -> String;
+    async fn into_name(self) -> String;
+}
+
+struct Named(&'static str);
+
+impl Describe<char> for Named {
+    type NameFuture<'a>
+        = Ready<String>
+    where
+        Self: 'a;
+    type DescribeFuture<'a, T: Debug>
+        = Ready<String>
+    where
+        Self: 'a;
+    type PrefixedFuture<'a, T: AsRef<str>>
+        = Ready<String>
+    where
+        Self: 'a,
+        T: 'a;
+    type IntoNameFuture = Ready<String>;
+
+    fn name(&self) -> Self::NameFuture<'_> {
+        ready(self.0.to_string())
+    }
+
+    fn describe<T: Debug>(&self, value: T) -> Self::DescribeFuture<'_, T> {
+        ready(format!("{}: {:?}", self.0, value))
+    }
+
+    fn prefixed<'a, T: AsRef<str>>(
+        &'a self,
+        value: &'a T,
+        prefix: char,
+    ) -> Self::PrefixedFuture<'a, T> {
+        ready(format!("{}{}{}", prefix, self.0, value.as_ref()))
+    }
+
+    fn into_name(self) -> Self::IntoNameFuture {
+        ready(self.0.to_string())
+    }
+}
+
+#[test]
+fn test_read() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    let mut buf = [0; 2];
+    assert_eq!(block_on(bytes.read(&mut buf)), 2);
+    assert_eq!(buf, [1, 2]);
+    assert_eq!(block_on(bytes.read_u8()), Some(3));
+    assert_eq!(block_on(bytes.read_u8()), None);
+}
+
+#[test]
+fn test_generics() {
+    let named = Named("foo");
+    assert_eq!(block_on(named.name()), "foo");
+    assert_eq!(block_on(named.describe(1)), "foo: 1");
+    assert_eq!(block_on(named.prefixed(&"bar", '@')), "@foobar");
+    assert_eq!(block_on(named.into_name()), "foo");
+}
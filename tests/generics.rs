@@ -0,0 +1,165 @@
+#![feature(type_alias_impl_trait)]
+
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    convert::TryInto,
+    task::{Context, Poll},
+};
+
+#[async_trait_ext]
+pub trait Codec<T>
+where
+    T: Copy,
+{
+    async fn encode(&self, value: T) -> Vec<u8>;
+    async fn decode<'a>(&self, buf: &'a [u8]) -> T;
+
+    #[async_fn(provided)]
+    async fn round_trip(&self, value: T) -> T {
+        let buf = self.encode(value).await;
+        self.decode(&buf).await
+    }
+
+    #[async_fn(provided)]
+    async fn encode_twice(&self, value: T) -> Vec<u8>
+    where
+        Self: Sync,
+    {
+        let mut buf = self.encode(value).await;
+        buf.extend(self.encode(value).await);
+        buf
+    }
+}
+
+struct Le;
+
+impl Codec<u32> for Le {
+    fn poll_encode(&self, value: u32, _: &mut Context) -> Poll<Vec<u8>> {
+        Poll::Ready(value.to_le_bytes().to_vec())
+    }
+
+    fn poll_decode(&self, buf: &[u8], _: &mut Context) -> Poll<u32> {
+        Poll::Ready(u32::from_le_bytes(buf.try_into().unwrap()))
+    }
+}
+
+impl Codec<u8> for Le {
+    fn poll_encode(&self, value: u8, _: &mut Context) -> Poll<Vec<u8>> {
+        Poll::Ready(vec![value])
+    }
+
+    fn poll_decode(&self, buf: &[u8], _: &mut Context) -> Poll<u8> {
+        Poll::Ready(buf[0])
+    }
+}
+
+#[test]
+fn test_type_param() {
+    assert_eq!(block_on(Le.encode(1u32)), vec![1, 0, 0, 0]);
+    assert_eq!(block_on(Le.encode(1u8)), vec![1]);
+    assert_eq!(block_on(CodecExt::<u32>::decode(&Le, &[2, 0, 0, 0])), 2);
+    assert_eq!(block_on(Le.round_trip(3u32)), 3);
+    assert_eq!(block_on(Le.encode_twice(4u8)), vec![4, 4]);
+}
+
+#[async_trait_ext]
+pub trait Parser<'src> {
+    async fn next_token(&mut self) -> Option<&'src str>;
+}
+
+struct Words<'src>(std::str::SplitWhitespace<'src>);
+
+impl<'src> Parser<'src> for Words<'src> {
+    fn poll_next_token(&mut self, _: &mut Context) -> Poll<Option<&'src str>> {
+        Poll::Ready(self.0.next())
+    }
+}
+
+#[test]
+fn test_lifetime_param() {
+    let mut words = Words("hello world".split_whitespace());
+    let first = block_on(words.next_token());
+    let second = block_on(words.next_token());
+    assert_eq!(first, Some("hello"));
+    assert_eq!(second, Some("world"));
+    assert_eq!(block_on(words.next_token()), None);
+}
+
+#[async_trait_ext]
+pub trait Chunks<const N: usize> {
+    async fn read_chunk(&mut self) -> [u8; N];
+}
+
+struct Counter(u8);
+
+impl<const N: usize> Chunks<N> for Counter {
+    fn poll_read_chunk(&mut self, _: &mut Context) -> Poll<[u8; N]> {
+        Poll::Ready([(); N].map(|_| {
+            self.0 += 1;
+            self.0
+        }))
+    }
+}
+
+#[test]
+fn test_const_param() {
+    let mut counter = Counter(0);
+    assert_eq!(block_on(ChunksExt::<2>::read_chunk(&mut counter)), [1, 2]);
+    assert_eq!(
+        block_on(ChunksExt::<3>::read_chunk(&mut counter)),
+        [3, 4, 5]
+    );
+}
+
+#[async_trait_ext]
+pub trait Sink<Item: Copy = u8> {
+    async fn send(&mut self, item: Item);
+}
+
+#[derive(Default)]
+struct Collect(Vec<u8>);
+
+impl Sink for Collect {
+    fn poll_send(&mut self, item: u8, _: &mut Context) -> Poll<()> {
+        self.0.push(item);
+        Poll::Ready(())
+    }
+}
+
+#[test]
+fn test_default_param() {
+    let mut collect = Collect::default();
+    block_on(collect.send(1));
+    block_on(<Collect as SinkExt>::send(&mut collect, 2));
+    assert_eq!(collect.0, [1, 2]);
+}
+
+pub mod dynamic {
+    use async_trait_ext::async_trait_ext;
+
+    #[async_trait_ext(dynamic)]
+    pub trait Source<T: Copy> {
+        async fn next(&mut self) -> T;
+    }
+}
+
+#[test]
+fn test_dynamic() {
+    use dynamic::{Source, SourceExt};
+
+    struct Count(u16);
+
+    impl Source<u16> for Count {
+        fn poll_next(&mut self, _: &mut Context) -> Poll<u16> {
+            self.0 += 1;
+            Poll::Ready(self.0)
+        }
+    }
+
+    let source: &mut dyn SourceExt<u16> = &mut Count(0);
+    assert_eq!(block_on(source.next()), 1);
+    assert_eq!(block_on(source.next()), 2);
+}
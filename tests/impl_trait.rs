@@ -0,0 +1,86 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::task::{Context, Poll};
+
+#[async_trait_ext]
+trait Sink {
+    #[async_fn(take_args)]
+    async fn write_all(&mut self, buf: impl AsRef<[u8]>);
+    #[async_fn(take_args)]
+    async fn extend(&mut self, bufs: impl IntoIterator<Item = impl AsRef<[u8]>>);
+    #[async_fn(take_args)]
+    async fn sum(&self, bytes: impl Iterator<Item = &u8>) -> u32;
+    async fn count(&self, pred: &mut impl FnMut(&u8) -> bool) -> usize;
+    async fn position(&self, pred: impl Fn(u8) -> bool + Copy) -> Option<usize>;
+    async fn contains(&self, byte: impl Into<u8> + Copy) -> bool;
+}
+
+struct Buffer(Vec<u8>);
+
+impl Sink for Buffer {
+    fn poll_write_all<B: AsRef<[u8]>>(&mut self, buf: &mut Option<B>, _: &mut Context) -> Poll<()> {
+        self.0.extend_from_slice(buf.take().unwrap().as_ref());
+        Poll::Ready(())
+    }
+
+    fn poll_extend<I: IntoIterator<Item = B>, B: AsRef<[u8]>>(
+        &mut self,
+        bufs: &mut Option<I>,
+        _: &mut Context,
+    ) -> Poll<()> {
+        for buf in bufs.take().unwrap() {
+            self.0.extend_from_slice(buf.as_ref());
+        }
+        Poll::Ready(())
+    }
+
+    fn poll_sum<'a, I: Iterator<Item = &'a u8>>(
+        &self,
+        bytes: &mut Option<I>,
+        _: &mut Context,
+    ) -> Poll<u32> {
+        Poll::Ready(bytes.take().unwrap().map(|&b| b as u32).sum())
+    }
+
+    fn poll_count<F: FnMut(&u8) -> bool>(&self, pred: &mut F, _: &mut Context) -> Poll<usize> {
+        Poll::Ready(self.0.iter().filter(|b| pred(b)).count())
+    }
+
+    fn poll_position<F: Fn(u8) -> bool + Copy>(
+        &self,
+        pred: F,
+        _: &mut Context,
+    ) -> Poll<Option<usize>> {
+        Poll::Ready(self.0.iter().position(|&b| pred(b)))
+    }
+
+    fn poll_contains<B: Into<u8> + Copy>(&self, byte: B, _: &mut Context) -> Poll<bool> {
+        Poll::Ready(self.0.contains(&byte.into()))
+    }
+}
+
+#[test]
+fn test_impl_trait() {
+    let mut buffer = Buffer(Vec::new());
+
+    block_on(buffer.write_all(b"ab"));
+    block_on(buffer.write_all(vec![b'c']));
+    block_on(buffer.extend([b"de".as_slice(), b"f"]));
+    block_on(buffer.write_all("g"));
+    assert_eq!(buffer.0, b"abcdefg");
+
+    let bytes = [1, 2, 3];
+    assert_eq!(block_on(buffer.sum(bytes.iter())), 6);
+
+    let mut seen = 0;
+    let count = block_on(buffer.count(&mut |&b| {
+        seen += 1;
+        b > b'c'
+    }));
+    assert_eq!((count, seen), (4, 7));
+
+    assert_eq!(block_on(buffer.position(|b| b == b'e')), Some(4));
+    assert!(block_on(buffer.contains(b'g')));
+}
@@ -1,5 +1,12 @@
+mod common;
+
 use async_trait_ext::async_trait_ext;
-use std::task::{Context, Poll};
+use common::block_on;
+use std::{
+    borrow::Cow,
+    fmt::{self, Formatter},
+    task::{Context, Poll},
+};
 
 #[test]
 fn test_lifetimes() {
@@ -21,3 +28,109 @@ fn test_lifetimes() {
         Baz.bar(&1).await;
     };
 }
+
+#[async_trait_ext]
+trait Dictionary {
+    async fn lookup(&self, word: &str) -> Option<&str>;
+    async fn first_word(text: &str) -> &str;
+    #[async_fn(take_args)]
+    async fn describe(&self, fallback: Cow<'_, str>) -> Cow<'_, str>;
+    async fn write(&self, f: &mut Formatter<'_>) -> fmt::Result;
+    async fn visit(&self, visitor: &mut (dyn FnMut(&str) + '_));
+}
+
+struct Words(Vec<String>);
+
+impl Dictionary for Words {
+    fn poll_lookup(&self, word: &str, _: &mut Context) -> Poll<Option<&str>> {
+        Poll::Ready(
+            self.0
+                .iter()
+                .find(|w| w.starts_with(word))
+                .map(String::as_str),
+        )
+    }
+
+    fn poll_first_word<'a>(text: &'a str, _: &mut Context) -> Poll<&'a str> {
+        Poll::Ready(text.split(' ').next().unwrap())
+    }
+
+    fn poll_describe(
+        &self,
+        fallback: &mut Option<Cow<'_, str>>,
+        _: &mut Context,
+    ) -> Poll<Cow<'_, str>> {
+        match self.0.first() {
+            Some(word) => Poll::Ready(Cow::Borrowed(word)),
+            None => Poll::Ready(Cow::Owned(fallback.take().unwrap().into_owned())),
+        }
+    }
+
+    fn poll_write(&self, f: &mut Formatter<'_>, _: &mut Context) -> Poll<fmt::Result> {
+        Poll::Ready(write!(f, "{}", self.0.join(", ")))
+    }
+
+    fn poll_visit(&self, visitor: &mut (dyn FnMut(&str) + '_), _: &mut Context) -> Poll<()> {
+        self.0.iter().for_each(|word| visitor(word));
+        Poll::Ready(())
+    }
+}
+
+impl fmt::Display for Words {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        block_on(self.write(f))
+    }
+}
+
+#[test]
+fn test_elided_lifetimes() {
+    let words = Words(vec!["apple".to_owned(), "banana".to_owned()]);
+
+    // the query only has to live as long as the future, not as long as the result
+    let found = {
+        let query = String::from("ban");
+        block_on(words.lookup(&query))
+    };
+    assert_eq!(found, Some("banana"));
+
+    assert_eq!(block_on(Words::first_word("hello world")), "hello");
+
+    let fallback = String::from("nothing");
+    assert_eq!(
+        block_on(words.describe(Cow::Borrowed(&fallback))),
+        Cow::Borrowed("apple")
+    );
+    assert_eq!(
+        block_on(Words(vec![]).describe(Cow::Borrowed(&fallback))),
+        Cow::<str>::Owned("nothing".to_owned())
+    );
+
+    assert_eq!(words.to_string(), "apple, banana");
+
+    let mut seen = Vec::new();
+    block_on(words.visit(&mut |word| seen.push(word.len())));
+    assert_eq!(seen, vec![5, 6]);
+}
+
+#[async_trait_ext(dynamic)]
+trait Buffer {
+    async fn peek(&self, len: &usize) -> &[u8];
+}
+
+struct Bytes(Vec<u8>);
+
+impl Buffer for Bytes {
+    fn poll_peek(&self, len: &usize, _: &mut Context) -> Poll<&[u8]> {
+        Poll::Ready(&self.0[..*len])
+    }
+}
+
+#[test]
+fn test_elided_lifetimes_dynamic() {
+    let bytes: &dyn BufferExt = &Bytes(vec![1, 2, 3]);
+    let peeked = {
+        let len = 2;
+        block_on(bytes.peek(&len))
+    };
+    assert_eq!(peeked, &[1, 2]);
+}
@@ -1,7 +1,8 @@
 #![feature(type_alias_impl_trait)]
 
-#[test]
-fn test_mut_pattern() {
+// opaque types can't be defined by items nested in a function body and
+// private modules make the current nightly panic
+pub mod inner {
     use async_trait_ext::async_trait_ext;
 
     #[async_trait_ext(dynamic)]
@@ -26,3 +27,6 @@ fn test_mut_pattern() {
         }
     }
 }
+
+#[test]
+fn test_mut_pattern() {}
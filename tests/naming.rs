@@ -0,0 +1,82 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    future::{ready, Ready},
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+#[async_trait_ext(ext = "BufReadExt", future = "{method}Future")]
+pub trait AsyncBufRead {
+    #[async_fn(poll = "poll_fill_buf", future = "FillBuf")]
+    async fn fill(&mut self) -> usize;
+    async fn consume(&mut self, amount: usize);
+    #[async_fn(owned, future = "Available")]
+    async fn available(&self) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn skip(&mut self) -> usize {
+        let len = self.available().await;
+        self.consume(len).await;
+        len
+    }
+}
+
+struct Bytes(Vec<u8>);
+
+impl AsyncBufRead for Bytes {
+    fn poll_fill_buf(&mut self, _: &mut Context) -> Poll<usize> {
+        Poll::Ready(self.0.len())
+    }
+
+    fn poll_consume(&mut self, amount: usize, _: &mut Context) -> Poll<()> {
+        self.0.drain(..amount);
+        Poll::Ready(())
+    }
+
+    fn poll_available(&self, _: &mut Context) -> Poll<usize> {
+        Poll::Ready(self.0.len())
+    }
+}
+
+#[test]
+fn test_naming() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    let fill: FillBuf<Bytes> = BufReadExt::fill(&mut bytes);
+    assert_eq!(block_on(fill), 3);
+    let len: Available<Bytes> = bytes.available();
+    assert_eq!(block_on(len), 3);
+    let skip: SkipFuture<Bytes> = bytes.skip();
+    assert_eq!(block_on(skip), 3);
+    let consume: ConsumeFuture<Bytes> = bytes.consume(0);
+    block_on(consume);
+
+    let bytes = Arc::new(Bytes(vec![1]));
+    let len: AvailableOwned<Bytes> = bytes.available_owned();
+    assert_eq!(block_on(len), 1);
+}
+
+#[async_trait_ext(gat)]
+pub trait Source {
+    #[async_fn(future = "Next")]
+    async fn next<'a>(&'a mut self) -> u32;
+}
+
+struct Numbers(u32);
+
+impl Source for Numbers {
+    type Next<'a> = Ready<u32>;
+
+    fn next<'a>(&'a mut self) -> Self::Next<'a> {
+        self.0 += 1;
+        ready(self.0)
+    }
+}
+
+#[test]
+fn test_gat() {
+    let mut numbers = Numbers(0);
+    assert_eq!(block_on(numbers.next()), 1);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[async_trait_ext(rpitit, native = "NativeRead")]
+pub trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+    async fn peek<const N: usize>(&self) -> Option<u8>;
+
+    #[async_fn(provided)]
+    async fn read_u8(&mut self) -> Option<u8> {
+        let mut buf = [0];
+        match self.read(&mut buf).await {
+            0 => None,
+            _ => Some(buf[0]),
+        }
+    }
+}
+
+#[async_trait_ext(dynamic, native = "NativeStream")]
+pub trait Stream {
+    type Item;
+
+    async fn next(self: Pin<&mut Self>) -> Option<Self::Item>;
+
+    #[async_fn(provided, boxed_local)]
+    async fn count(&mut self) -> usize
+    where
+        Self: Unpin,
+    {
+        let mut count = 0;
+        while Pin::new(&mut *self).next().await.is_some() {
+            count += 1;
+        }
+        count
+    }
+}
+
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    fn poll_read(&mut self, buf: &mut [u8], _: &mut Context) -> Poll<usize> {
+        let len = buf.len().min(self.0.len());
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0.drain(..len);
+        Poll::Ready(len)
+    }
+
+    fn poll_peek<const N: usize>(&self, _: &mut Context) -> Poll<Option<u8>> {
+        Poll::Ready(self.0.get(N).copied())
+    }
+}
+
+impl Stream for Bytes {
+    type Item = u8;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<u8>> {
+        let bytes = Pin::into_inner(self);
+        Poll::Ready((!bytes.0.is_empty()).then(|| bytes.0.remove(0)))
+    }
+}
+
+/// implements the native trait directly
+struct Zeros(usize);
+
+impl NativeRead for Zeros {
+    async fn read(&mut self, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(self.0);
+        buf[..len].fill(0);
+        self.0 -= len;
+        len
+    }
+
+    async fn peek<const N: usize>(&self) -> Option<u8> {
+        (N < self.0).then_some(0)
+    }
+
+    async fn read_u8(&mut self) -> Option<u8> {
+        match self.read(&mut [0]).await {
+            0 => None,
+            _ => Some(0),
+        }
+    }
+}
+
+/// generic code using native async methods
+async fn read_to_end<R: NativeRead>(reader: &mut R) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    while let Some(byte) = reader.read_u8().await {
+        bytes.push(byte);
+    }
+    bytes
+}
+
+async fn sum<S: NativeStream<Item = u8> + Unpin>(stream: &mut S) -> u32 {
+    let mut sum = 0;
+    while let Some(byte) = Pin::new(&mut *stream).next().await {
+        sum += u32::from(byte);
+    }
+    sum
+}
+
+#[test]
+fn test_read() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    assert_eq!(block_on(NativeRead::peek::<1>(&bytes)), Some(2));
+    assert_eq!(block_on(read_to_end(&mut bytes)), [1, 2, 3]);
+    assert_eq!(block_on(read_to_end(&mut Zeros(2))), [0, 0]);
+}
+
+#[test]
+fn test_stream() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    assert_eq!(block_on(sum(&mut bytes)), 6);
+
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    assert_eq!(block_on(NativeStream::count(&mut bytes)), 3);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    future::{ready, Future},
+    task::{Context, Poll},
+};
+
+#[async_trait_ext(rpitit)]
+pub trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, overridable)]
+    async fn read_u8(&mut self) -> Option<u8> {
+        let mut buf = [0];
+        match self.read(&mut buf).await {
+            0 => None,
+            _ => Some(buf[0]),
+        }
+    }
+}
+
+#[async_trait_ext(dynamic)]
+pub trait Stream: Send {
+    type Item: Send;
+
+    async fn next(&mut self) -> Option<Self::Item>;
+
+    #[async_fn(provided, boxed, overridable)]
+    async fn skip_one(&mut self) -> Option<Self::Item> {
+        self.next().await?;
+        self.next().await
+    }
+}
+
+/// uses the provided methods
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    fn poll_read(&mut self, buf: &mut [u8], _: &mut Context) -> Poll<usize> {
+        let len = buf.len().min(self.0.len());
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0.drain(..len);
+        Poll::Ready(len)
+    }
+}
+
+impl Stream for Bytes {
+    type Item = u8;
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<u8>> {
+        Poll::Ready((!self.0.is_empty()).then(|| self.0.remove(0)))
+    }
+}
+
+/// overrides the provided methods, reading from the back
+struct Reversed(Vec<u8>);
+
+impl Read for Reversed {
+    fn poll_read(&mut self, _: &mut [u8], _: &mut Context) -> Poll<usize> {
+        unreachable!()
+    }
+
+    fn provided_read_u8(&mut self) -> impl Future<Output = Option<u8>> {
+        ready(self.0.pop())
+    }
+}
+
+impl Stream for Reversed {
+    type Item = u8;
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<u8>> {
+        unreachable!()
+    }
+
+    fn provided_skip_one<'a, 'b>(&'a mut self) -> StreamSkipOne<'b, u8>
+    where
+        'a: 'b,
+    {
+        self.0.pop();
+        Box::pin(ready(self.0.pop()))
+    }
+}
+
+#[test]
+fn test_provided() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    assert_eq!(block_on(bytes.read_u8()), Some(1));
+
+    assert_eq!(block_on(bytes.skip_one()), Some(3));
+    assert_eq!(block_on(bytes.skip_one()), None);
+}
+
+#[test]
+fn test_overridden() {
+    let mut reversed = Reversed(vec![1, 2, 3]);
+    assert_eq!(block_on(reversed.read_u8()), Some(3));
+
+    assert_eq!(block_on(reversed.skip_one()), Some(1));
+    assert_eq!(block_on(reversed.skip_one()), None);
+}
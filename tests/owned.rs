@@ -0,0 +1,78 @@
+use async_trait_ext::async_trait_ext;
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+
+fn spawn<F: Future + 'static>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+            return res;
+        }
+    }
+}
+
+#[async_trait_ext]
+pub trait Lock {
+    #[async_fn(owned)]
+    async fn lock(&self) -> bool;
+}
+
+struct Mutex(AtomicBool);
+
+impl Lock for Mutex {
+    fn poll_lock(&self, _: &mut Context) -> Poll<bool> {
+        Poll::Ready(!self.0.swap(true, Ordering::SeqCst))
+    }
+}
+
+#[test]
+fn test_owned() {
+    let mutex = Arc::new(Mutex(AtomicBool::new(false)));
+    assert!(spawn(mutex.clone().lock_owned()));
+    assert!(!spawn(mutex.clone().lock_owned()));
+    assert_eq!(Arc::strong_count(&mutex), 1);
+}
+
+pub mod dynamic {
+    use async_trait_ext::async_trait_ext;
+
+    #[async_trait_ext(dynamic)]
+    pub trait Counter {
+        #[async_fn(owned)]
+        async fn increment(&mut self, by: u32) -> u32;
+        #[async_fn(owned)]
+        async fn get(&self) -> u32;
+    }
+}
+
+#[test]
+fn test_owned_dynamic() {
+    use dynamic::{Counter, CounterExt};
+
+    struct Count(u32);
+
+    impl Counter for Count {
+        fn poll_increment(&mut self, by: u32, _: &mut Context) -> Poll<u32> {
+            self.0 += by;
+            Poll::Ready(self.0)
+        }
+
+        fn poll_get(&self, _: &mut Context) -> Poll<u32> {
+            Poll::Ready(self.0)
+        }
+    }
+
+    let count: Box<dyn CounterExt> = Box::new(Count(1));
+    assert_eq!(spawn(count.increment_owned(2)), 3);
+
+    let count: Arc<dyn CounterExt> = Arc::new(Count(1));
+    assert_eq!(spawn(count.get_owned()), 1);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    marker::PhantomPinned,
+    pin::{pin, Pin},
+    task::{Context, Poll},
+};
+
+#[async_trait_ext]
+pub trait AsyncRead {
+    async fn read<'a>(self: Pin<&'a mut Self>, buf: &'a mut [u8]) -> usize;
+    async fn close(self: Pin<&mut Self>);
+}
+
+struct Zeroes {
+    remaining: usize,
+    _pin: PhantomPinned,
+}
+
+impl AsyncRead for Zeroes {
+    fn poll_read<'a>(self: Pin<&'a mut Self>, buf: &'a mut [u8], _: &mut Context) -> Poll<usize> {
+        // SAFETY: `remaining` isn't structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let len = buf.len().min(this.remaining);
+        buf[..len].fill(0);
+        this.remaining -= len;
+        Poll::Ready(len)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+struct Ones;
+
+impl AsyncRead for Ones {
+    fn poll_read<'a>(self: Pin<&'a mut Self>, buf: &'a mut [u8], _: &mut Context) -> Poll<usize> {
+        buf.fill(1);
+        Poll::Ready(buf.len())
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+#[test]
+fn test_pinned() {
+    let mut zeroes = pin!(Zeroes {
+        remaining: 3,
+        _pin: PhantomPinned,
+    });
+    let mut buf = [1; 2];
+    assert_eq!(block_on(zeroes.as_mut().read_pinned(&mut buf)), 2);
+    assert_eq!(block_on(zeroes.as_mut().read_pinned(&mut buf)), 1);
+    assert_eq!(block_on(zeroes.as_mut().read_pinned(&mut buf)), 0);
+    block_on(zeroes.close_pinned());
+}
+
+#[test]
+fn test_unpin() {
+    let mut ones = Ones;
+    let mut buf = [0; 2];
+    assert_eq!(block_on(ones.read(&mut buf)), 2);
+    assert_eq!(buf, [1; 2]);
+    block_on(ones.close());
+}
+
+pub mod dynamic {
+    use super::*;
+
+    #[async_trait_ext(dynamic)]
+    pub trait AsyncWrite {
+        #[async_fn(on_cancel)]
+        async fn write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8]) -> usize;
+    }
+}
+
+#[test]
+fn test_pinned_dynamic() {
+    use dynamic::{AsyncWrite, AsyncWriteExt};
+
+    struct Sink;
+
+    impl AsyncWrite for Sink {
+        fn poll_write<'a>(self: Pin<&'a mut Self>, buf: &'a [u8], _: &mut Context) -> Poll<usize> {
+            Poll::Ready(buf.len())
+        }
+    }
+
+    let mut sink = Sink;
+    assert_eq!(block_on(sink.write(&[1, 2, 3])), 3);
+    let sink: Pin<&mut Sink> = Pin::new(&mut sink);
+    let sink: Pin<&mut dyn AsyncWriteExt> = sink;
+    assert_eq!(block_on(sink.write_pinned(&[1, 2])), 2);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+#[async_trait_ext]
+pub trait Connection {
+    async fn close(self: Box<Self>) -> u32;
+    async fn id(self: Rc<Self>) -> u32;
+    async fn send(self: Arc<Self>, data: u32) -> u32;
+    async fn recv(self: &Arc<Self>) -> u32;
+}
+
+pub mod dynamic {
+    use super::*;
+    use async_trait_ext::async_trait_ext;
+
+    #[async_trait_ext(dynamic)]
+    pub trait Connection {
+        async fn close(self: Box<Self>) -> u32;
+        async fn id(self: Rc<Self>) -> u32;
+        async fn send(self: Arc<Self>, data: u32) -> u32;
+        async fn recv(self: &Arc<Self>) -> u32;
+    }
+}
+
+struct Tcp(u32);
+
+impl Connection for Tcp {
+    fn poll_close(&mut self, _: &mut Context) -> Poll<u32> {
+        self.0 += 1;
+        Poll::Ready(self.0)
+    }
+
+    fn poll_id(&self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0)
+    }
+
+    fn poll_send(&self, data: u32, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0 + data)
+    }
+
+    fn poll_recv(&self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0)
+    }
+}
+
+impl dynamic::Connection for Tcp {
+    fn poll_close(&mut self, _: &mut Context) -> Poll<u32> {
+        self.0 += 1;
+        Poll::Ready(self.0)
+    }
+
+    fn poll_id(&self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0)
+    }
+
+    fn poll_send(&self, data: u32, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0 + data)
+    }
+
+    fn poll_recv(&self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(self.0)
+    }
+}
+
+fn assert_static<T: 'static>(value: T) -> T {
+    value
+}
+
+#[test]
+fn test_pointers() {
+    assert_eq!(block_on(assert_static(Box::new(Tcp(1)).close())), 2);
+    assert_eq!(block_on(assert_static(Rc::new(Tcp(1)).id())), 1);
+
+    let tcp = Arc::new(Tcp(1));
+    assert_eq!(block_on(assert_static(tcp.clone().send(2))), 3);
+    assert_eq!(block_on(assert_static(tcp.recv())), 1);
+    assert_eq!(Arc::strong_count(&tcp), 1);
+}
+
+#[test]
+fn test_pointers_dynamic() {
+    use dynamic::ConnectionExt;
+
+    let tcp: Box<dyn ConnectionExt> = Box::new(Tcp(1));
+    assert_eq!(block_on(assert_static(tcp.close())), 2);
+    let tcp: Rc<dyn ConnectionExt> = Rc::new(Tcp(1));
+    assert_eq!(block_on(assert_static(tcp.id())), 1);
+    let tcp: Arc<dyn ConnectionExt> = Arc::new(Tcp(1));
+    assert_eq!(block_on(assert_static(tcp.send(2))), 3);
+
+    let tcp = Arc::new(Tcp(1));
+    assert_eq!(
+        block_on(assert_static(dynamic::ConnectionExt::recv(&tcp))),
+        1
+    );
+}
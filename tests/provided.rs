@@ -5,24 +5,30 @@ use std::task::{Context, Poll};
 
 #[test]
 fn test_provided() {
-    #[async_trait_ext]
-    trait Foo {
-        async fn bar(&self, i: u32);
+    // opaque types can't be defined by items nested in a function body
+    mod inner {
+        use super::*;
 
-        #[async_fn(provided)]
-        async fn baz(&self) {
-            self.bar(0);
+        #[async_trait_ext]
+        pub trait Foo {
+            async fn bar(&self, i: u32);
+
+            #[async_fn(provided)]
+            async fn baz(&self) {
+                self.bar(0);
+            }
         }
-    }
 
-    #[derive(Clone, Copy)]
-    struct Quux;
+        #[derive(Clone, Copy)]
+        pub struct Quux;
 
-    impl Foo for Quux {
-        fn poll_bar(&self, _: u32, _: &mut Context) -> Poll<()> {
-            unimplemented!()
+        impl Foo for Quux {
+            fn poll_bar(&self, _: u32, _: &mut Context) -> Poll<()> {
+                unimplemented!()
+            }
         }
     }
+    use inner::{FooExt, Quux};
 
     let _ = async {
         Quux.bar(0).await;
@@ -54,3 +60,55 @@ pub trait Dynamic {
         *val = self.method2(*val).await + 1;
     }
 }
+
+#[async_trait_ext(dynamic(send, sync))]
+pub trait DynamicSend {
+    async fn method1(&self, val: u32) -> u32;
+
+    #[async_fn(provided)]
+    async fn method2(&mut self, val: u32) -> u32 {
+        self.method1(val - 1).await + 1
+    }
+}
+
+#[async_trait_ext]
+pub trait StaticGeneric<T: Copy> {
+    async fn next(&mut self) -> T;
+
+    #[async_fn(provided)]
+    async fn pair(&mut self) -> (T, T) {
+        (self.next().await, self.next().await)
+    }
+}
+
+#[async_trait_ext(dynamic)]
+pub trait DynamicGeneric<T: Copy> {
+    async fn next(&mut self) -> T;
+
+    #[async_fn(provided)]
+    async fn pair(&mut self) -> (T, T) {
+        (self.next().await, self.next().await)
+    }
+}
+
+#[async_trait_ext(dynamic)]
+pub trait DynamicAssoc {
+    type Item: Copy;
+
+    async fn next(&mut self) -> Self::Item;
+
+    #[async_fn(provided)]
+    async fn pair(&mut self) -> (Self::Item, Self::Item) {
+        (self.next().await, self.next().await)
+    }
+}
+
+#[async_trait_ext]
+pub trait StaticImplTrait {
+    async fn write(&mut self, buf: &[u8]);
+
+    #[async_fn(provided)]
+    async fn write_str(&mut self, s: impl AsRef<str>) {
+        self.write(s.as_ref().as_bytes()).await
+    }
+}
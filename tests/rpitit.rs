@@ -0,0 +1,102 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::task::{Context, Poll};
+
+#[async_trait_ext(rpitit)]
+pub trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided)]
+    async fn read_u8(&mut self) -> Option<u8> {
+        let mut buf = [0];
+        match self.read(&mut buf).await {
+            0 => None,
+            _ => Some(buf[0]),
+        }
+    }
+
+    #[async_fn(provided)]
+    async fn read_all(&mut self, mut sink: impl FnMut(u8)) -> usize {
+        let mut len = 0;
+        while let Some(byte) = self.read_u8().await {
+            sink(byte);
+            len += 1;
+        }
+        len
+    }
+}
+
+#[async_trait_ext(dynamic, rpitit)]
+pub trait Stream {
+    type Item;
+
+    async fn next(&mut self) -> Option<Self::Item>;
+
+    #[async_fn(provided)]
+    async fn pair(&mut self) -> Option<(Self::Item, Self::Item)> {
+        Some((self.next().await?, self.next().await?))
+    }
+}
+
+#[async_trait_ext(rpitit)]
+pub trait Source<T: Clone> {
+    async fn get(&self) -> T;
+
+    #[async_fn(provided)]
+    async fn get_twice(&self) -> (T, T) {
+        let value = self.get().await;
+        (value.clone(), value)
+    }
+}
+
+struct Bytes(Vec<u8>);
+
+impl Read for Bytes {
+    fn poll_read(&mut self, buf: &mut [u8], _: &mut Context) -> Poll<usize> {
+        let len = buf.len().min(self.0.len());
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0.drain(..len);
+        Poll::Ready(len)
+    }
+}
+
+impl Stream for Bytes {
+    type Item = u8;
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<u8>> {
+        Poll::Ready((!self.0.is_empty()).then(|| self.0.remove(0)))
+    }
+}
+
+impl Source<String> for Bytes {
+    fn poll_get(&self, _: &mut Context) -> Poll<String> {
+        Poll::Ready(String::from_utf8(self.0.clone()).unwrap())
+    }
+}
+
+#[test]
+fn test_rpitit() {
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    assert_eq!(block_on(bytes.read_u8()), Some(1));
+
+    let mut seen = Vec::new();
+    assert_eq!(block_on(bytes.read_all(|byte| seen.push(byte))), 2);
+    assert_eq!(seen, vec![2, 3]);
+
+    let mut bytes = Bytes(vec![1, 2, 3]);
+    assert_eq!(block_on(bytes.pair()), Some((1, 2)));
+    assert_eq!(block_on(bytes.pair()), None);
+
+    // the methods without default blocks can still be called on trait objects
+    let mut bytes = Bytes(vec![4]);
+    let stream: &mut dyn StreamExt<Item = u8> = &mut bytes;
+    assert_eq!(block_on(stream.next()), Some(4));
+
+    let bytes = Bytes(b"hi".to_vec());
+    assert_eq!(
+        block_on(bytes.get_twice()),
+        ("hi".to_owned(), "hi".to_owned())
+    );
+}
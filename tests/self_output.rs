@@ -0,0 +1,48 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::task::{Context, Poll};
+
+#[async_trait_ext]
+pub trait Connect {
+    async fn connect(port: u16) -> Result<Self, String>;
+    async fn duplicate(&self) -> Self;
+    async fn pair(&self) -> (Self, Option<Box<Self>>);
+}
+
+#[derive(Debug, PartialEq)]
+struct Connection(u16);
+
+impl Connect for Connection {
+    fn poll_connect(port: u16, _: &mut Context) -> Poll<Result<Self, String>> {
+        if port == 0 {
+            Poll::Ready(Err("invalid port".to_owned()))
+        } else {
+            Poll::Ready(Ok(Connection(port)))
+        }
+    }
+
+    fn poll_duplicate(&self, _: &mut Context) -> Poll<Self> {
+        Poll::Ready(Connection(self.0))
+    }
+
+    fn poll_pair(&self, _: &mut Context) -> Poll<(Self, Option<Box<Self>>)> {
+        Poll::Ready((Connection(self.0), Some(Box::new(Connection(self.0)))))
+    }
+}
+
+#[test]
+fn test_self_output() {
+    let conn = block_on(Connection::connect(80)).unwrap();
+    assert_eq!(conn, Connection(80));
+    assert_eq!(
+        block_on(Connection::connect(0)),
+        Err("invalid port".to_owned())
+    );
+    assert_eq!(block_on(conn.duplicate()), Connection(80));
+    assert_eq!(
+        block_on(conn.pair()),
+        (Connection(80), Some(Box::new(Connection(80))))
+    );
+}
@@ -0,0 +1,85 @@
+use async_trait_ext::async_trait_ext;
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Default)]
+pub struct Progress {
+    filled: usize,
+}
+
+#[async_trait_ext]
+pub trait ReadExact {
+    #[async_fn(state = "Progress")]
+    async fn read_exact<'a>(&'a self, buf: &'a mut [u8]);
+}
+
+/// yields one byte per poll
+struct Counter;
+
+impl ReadExact for Counter {
+    fn poll_read_exact<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+        state: &mut Progress,
+        _: &mut Context,
+    ) -> Poll<()> {
+        if state.filled == buf.len() {
+            return Poll::Ready(());
+        }
+
+        buf[state.filled] = state.filled as u8;
+        state.filled += 1;
+        Poll::Pending
+    }
+}
+
+#[test]
+fn test_state() {
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let mut a = [0; 3];
+    let mut b = [0; 2];
+    {
+        // both futures borrow the same reader, but keep their own progress
+        let mut fut_a = pin!(Counter.read_exact(&mut a));
+        let mut fut_b = pin!(Counter.read_exact(&mut b));
+        while fut_a.as_mut().poll(&mut cx).is_pending() | fut_b.as_mut().poll(&mut cx).is_pending()
+        {
+        }
+    }
+
+    assert_eq!(a, [0, 1, 2]);
+    assert_eq!(b, [0, 1]);
+}
+
+#[test]
+fn test_state_dynamic() {
+    #[async_trait_ext(dynamic)]
+    trait Retry {
+        #[async_fn(state = "u32")]
+        async fn connect(&self, attempts: u32) -> u32;
+    }
+
+    struct Flaky;
+
+    impl Retry for Flaky {
+        fn poll_connect(&self, attempts: u32, state: &mut u32, _: &mut Context) -> Poll<u32> {
+            *state += 1;
+            if *state < attempts {
+                Poll::Pending
+            } else {
+                Poll::Ready(*state)
+            }
+        }
+    }
+
+    let retry: &dyn RetryExt = &Flaky;
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut fut = pin!(retry.connect(3));
+    assert!(fut.as_mut().poll(&mut cx).is_pending());
+    assert!(fut.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(3));
+}
@@ -0,0 +1,251 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+fn assert_unpin<T: Unpin>(value: T) -> T {
+    value
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Eof;
+
+#[async_trait_ext]
+pub trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn read_u8(&mut self) -> Option<u8> {
+        let mut buf: [u8; 1] = [0];
+        let len = self.read(&mut buf).await;
+        if len == 0 {
+            None
+        } else {
+            Some(buf[0])
+        }
+    }
+
+    #[async_fn(provided, state_machine)]
+    async fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Eof> {
+        let mut filled: usize = 0;
+        while filled < buf.len() {
+            let len = self.read(&mut buf[filled..]).await;
+            if len == 0 {
+                return Err(Eof);
+            }
+            filled += len;
+        }
+        Ok(())
+    }
+
+    #[async_fn(provided, state_machine)]
+    async fn read_to_end(&mut self, sink: &mut Vec<u8>) -> usize {
+        let mut total: usize = 0;
+        let mut buf: [u8; 4] = [0; 4];
+        loop {
+            let len = self.read(&mut buf).await;
+            if len == 0 {
+                break;
+            }
+            sink.extend_from_slice(&buf[..len]);
+            total += len;
+        }
+        total
+    }
+}
+
+/// returns at most two bytes per read and is pending every other poll
+struct Bytes {
+    data: Vec<u8>,
+    ready: bool,
+}
+
+impl Bytes {
+    fn new(data: &[u8]) -> Self {
+        Bytes {
+            data: data.to_vec(),
+            ready: false,
+        }
+    }
+}
+
+impl Read for Bytes {
+    fn poll_read(&mut self, buf: &mut [u8], _: &mut Context) -> Poll<usize> {
+        self.ready = !self.ready;
+        if !self.ready {
+            return Poll::Pending;
+        }
+
+        let len = buf.len().min(self.data.len()).min(2);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data.drain(..len);
+        Poll::Ready(len)
+    }
+}
+
+#[async_trait_ext]
+pub trait Write {
+    async fn write(&mut self, byte: u8) -> Result<(), Eof>;
+    #[async_fn(state = "usize")]
+    async fn flush(&mut self) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn write_all(&mut self, bytes: &[u8], flush: bool) -> Result<usize, Eof> {
+        let mut written: usize = 0;
+        for &byte in bytes {
+            if byte == 0 {
+                break;
+            }
+            written += 1;
+        }
+
+        let mut i: usize = 0;
+        while i < written {
+            self.write(bytes[i]).await?;
+            i += 1;
+        }
+
+        match flush {
+            true => {
+                let polls = self.flush().await;
+                Ok(written + polls)
+            }
+            false => Ok(written),
+        }
+    }
+}
+
+struct Buffer {
+    data: Vec<u8>,
+    capacity: usize,
+}
+
+impl Write for Buffer {
+    fn poll_write(&mut self, byte: u8, _: &mut Context) -> Poll<Result<(), Eof>> {
+        if self.data.len() == self.capacity {
+            return Poll::Ready(Err(Eof));
+        }
+        self.data.push(byte);
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_flush(&mut self, polls: &mut usize, _: &mut Context) -> Poll<usize> {
+        *polls += 1;
+        if *polls < 3 {
+            Poll::Pending
+        } else {
+            Poll::Ready(*polls)
+        }
+    }
+}
+
+#[async_trait_ext(dynamic)]
+pub trait Stream {
+    type Item;
+
+    async fn next(&mut self) -> Option<Self::Item>;
+
+    #[async_fn(provided, state_machine)]
+    async fn pair(&mut self) -> Option<(Self::Item, Self::Item)> {
+        let first = self.next().await?;
+        let second = self.next().await?;
+        Some((first, second))
+    }
+}
+
+struct Numbers(u32);
+
+impl Stream for Numbers {
+    type Item = u32;
+
+    fn poll_next(&mut self, _: &mut Context) -> Poll<Option<u32>> {
+        if self.0 == 0 {
+            return Poll::Ready(None);
+        }
+        self.0 -= 1;
+        Poll::Ready(Some(self.0))
+    }
+}
+
+#[async_trait_ext]
+pub trait Source<T: Clone> {
+    async fn get(&self) -> T;
+
+    #[async_fn(provided, state_machine)]
+    async fn get_twice(&self) -> (T, T) {
+        let value = self.get().await;
+        if false {
+            self.get().await;
+        }
+        (value.clone(), value)
+    }
+}
+
+impl Source<String> for &str {
+    fn poll_get(&self, _: &mut Context) -> Poll<String> {
+        Poll::Ready(self.to_string())
+    }
+}
+
+#[test]
+fn test_read() {
+    let mut bytes = Bytes::new(&[1, 2, 3, 4, 5]);
+    assert_eq!(block_on(bytes.read_u8()), Some(1));
+
+    let mut buf = [0; 3];
+    let fut: ReadReadExact<'_, '_, Bytes> = assert_unpin(bytes.read_exact(&mut buf));
+    assert_eq!(block_on(fut), Ok(()));
+    assert_eq!(buf, [2, 3, 4]);
+
+    let mut buf = [0; 2];
+    assert_eq!(block_on(bytes.read_exact(&mut buf)), Err(Eof));
+
+    let mut bytes = Bytes::new(&[1, 2, 3, 4, 5]);
+    let mut sink = Vec::new();
+    assert_eq!(block_on(bytes.read_to_end(&mut sink)), 5);
+    assert_eq!(sink, [1, 2, 3, 4, 5]);
+    assert_eq!(block_on(bytes.read_u8()), None);
+}
+
+#[test]
+fn test_write() {
+    let mut buffer = Buffer {
+        data: Vec::new(),
+        capacity: 3,
+    };
+    assert_eq!(block_on(buffer.write_all(&[1, 2, 0, 3], true)), Ok(5));
+    assert_eq!(block_on(buffer.write_all(&[3], false)), Ok(1));
+    assert_eq!(block_on(buffer.write_all(&[4], false)), Err(Eof));
+    assert_eq!(buffer.data, [1, 2, 3]);
+}
+
+#[test]
+fn test_dynamic() {
+    let mut numbers = Numbers(3);
+    let stream: &mut dyn StreamExt<Item = u32> = &mut numbers;
+    assert_eq!(block_on(stream.pair()), Some((2, 1)));
+    assert_eq!(block_on(stream.pair()), None);
+}
+
+#[test]
+fn test_generic() {
+    assert_eq!(
+        block_on("hi".get_twice()),
+        ("hi".to_string(), "hi".to_string())
+    );
+}
+
+#[test]
+#[should_panic(expected = "`ReadReadU8` polled after completion")]
+fn test_poll_after_completion() {
+    let mut bytes = Bytes::new(&[1]);
+    let mut fut = bytes.read_u8();
+    let mut cx = Context::from_waker(Waker::noop());
+    while Pin::new(&mut fut).poll(&mut cx).is_pending() {}
+    let _ = Pin::new(&mut fut).poll(&mut cx);
+}
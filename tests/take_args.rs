@@ -0,0 +1,74 @@
+mod common;
+
+use async_trait_ext::async_trait_ext;
+use common::block_on;
+use std::task::{Context, Poll};
+
+#[async_trait_ext]
+pub trait Sink {
+    #[async_fn(take_args)]
+    async fn send(&mut self, msg: Vec<u8>) -> usize;
+
+    #[async_fn(take_args)]
+    async fn close(self) -> Vec<Vec<u8>>;
+}
+
+#[derive(Default)]
+struct Buffer {
+    pending: bool,
+    sent: Vec<Vec<u8>>,
+}
+
+impl Sink for Buffer {
+    fn poll_send(&mut self, msg: &mut Option<Vec<u8>>, _: &mut Context) -> Poll<usize> {
+        // make sure the message survives a pending poll
+        self.pending = !self.pending;
+        if self.pending {
+            return Poll::Pending;
+        }
+
+        let msg = msg.take().unwrap();
+        let len = msg.len();
+        self.sent.push(msg);
+        Poll::Ready(len)
+    }
+
+    fn poll_close(this: &mut Option<Self>, _: &mut Context) -> Poll<Vec<Vec<u8>>> {
+        Poll::Ready(this.take().unwrap().sent)
+    }
+}
+
+#[test]
+fn test_take_args() {
+    let mut buffer = Buffer::default();
+    assert_eq!(block_on(buffer.send(vec![1, 2, 3])), 3);
+    assert_eq!(block_on(buffer.send(vec![4])), 1);
+    assert_eq!(block_on(buffer.close()), vec![vec![1, 2, 3], vec![4]]);
+}
+
+#[test]
+fn test_take_args_dynamic() {
+    #[async_trait_ext(dynamic)]
+    trait Sink {
+        #[async_fn(take_args)]
+        async fn send(&self, msg: String, len: usize) -> String;
+    }
+
+    struct Truncate;
+
+    impl Sink for Truncate {
+        fn poll_send(
+            &self,
+            msg: &mut Option<String>,
+            len: &mut Option<usize>,
+            _: &mut Context,
+        ) -> Poll<String> {
+            let mut msg = msg.take().unwrap();
+            msg.truncate(len.take().unwrap());
+            Poll::Ready(msg)
+        }
+    }
+
+    let sink: &dyn SinkExt = &Truncate;
+    assert_eq!(block_on(sink.send(String::from("foobar"), 3)), "foo");
+}
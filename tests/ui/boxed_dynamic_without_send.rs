@@ -0,0 +1,16 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext(dynamic)]
+trait Stream {
+    type Item: Send;
+
+    async fn next(&mut self) -> Option<Self::Item>;
+
+    #[async_fn(provided, boxed)]
+    async fn skip_one(&mut self) -> Option<Self::Item> {
+        self.next().await?;
+        self.next().await
+    }
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use async_trait_ext::{async_trait_ext, dispatch};
+use std::task::{Context, Poll};
+
+#[async_trait_ext(delegate)]
+pub trait Counter {
+    async fn next(&mut self) -> u32;
+}
+
+struct Zero;
+
+impl Counter for Zero {
+    fn poll_next(&mut self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(0)
+    }
+}
+
+#[dispatch(Counter, to = 0)]
+enum Counters {
+    Zero(Zero),
+}
+
+fn main() {}
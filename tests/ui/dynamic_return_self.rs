@@ -0,0 +1,8 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext(dynamic)]
+trait Builder {
+    async fn build(&self) -> Self;
+}
+
+fn main() {}
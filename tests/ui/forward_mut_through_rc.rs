@@ -0,0 +1,8 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext(forward(Rc))]
+trait Counter {
+    async fn next(&mut self) -> u32;
+}
+
+fn main() {}
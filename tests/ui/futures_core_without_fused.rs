@@ -0,0 +1,8 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext(futures_core)]
+trait Counter {
+    async fn next(&self) -> u32;
+}
+
+fn main() {}
@@ -0,0 +1,36 @@
+use async_trait_ext::async_trait_ext;
+use std::{
+    future::ready,
+    task::{Context, Poll},
+};
+
+#[async_trait_ext(dynamic)]
+pub trait Counter {
+    async fn next(&mut self) -> u32;
+
+    #[async_fn(provided, boxed_local, overridable)]
+    async fn skip_one(&mut self) -> u32 {
+        self.next().await;
+        self.next().await
+    }
+}
+
+struct Fixed;
+
+impl Counter for Fixed {
+    fn poll_next(&mut self, _: &mut Context) -> Poll<u32> {
+        Poll::Ready(101)
+    }
+
+    fn provided_skip_one<'a, 'b>(&'a mut self) -> CounterSkipOne<'b>
+    where
+        'a: 'b,
+    {
+        Box::pin(ready(42))
+    }
+}
+
+fn main() {
+    let counter: &mut dyn Counter = &mut Fixed;
+    let _ = counter.skip_one();
+}
@@ -0,0 +1,13 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn read_at_least_one(&mut self, buf: &mut [u8]) -> usize {
+        core::cmp::max(self.read(buf).await, 1)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Lock {
+    #[async_fn(on_cancel)]
+    async fn lock(&self);
+    async fn unlock(&self);
+
+    #[async_fn(provided, state_machine)]
+    async fn lock_and_unlock(&self) {
+        self.lock().await;
+        self.unlock().await;
+    }
+}
+
+fn main() {}
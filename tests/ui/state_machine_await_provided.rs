@@ -0,0 +1,24 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn read_u8(&mut self) -> Option<u8> {
+        let mut buf: [u8; 1] = [0];
+        let len = self.read(&mut buf).await;
+        if len == 0 {
+            None
+        } else {
+            Some(buf[0])
+        }
+    }
+
+    #[async_fn(provided, state_machine)]
+    async fn skip_u8(&mut self) {
+        self.read_u8().await;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Write {
+    async fn write(&mut self, byte: u8);
+
+    #[async_fn(provided, state_machine)]
+    async fn write_all(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write(*byte).await;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn skip(&mut self, buf: &mut [u8]) {
+        'outer: loop {
+            if self.read(buf).await == 0 {
+                break 'outer;
+            }
+        }
+    }
+}
+
+fn main() {}
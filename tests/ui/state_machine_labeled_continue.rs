@@ -0,0 +1,19 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn skip(&mut self, buf: &mut [u8]) {
+        'outer: loop {
+            let len = self.read(buf).await;
+            if len != 0 {
+                continue 'outer;
+            }
+            break;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn read_some(&mut self, buf: &mut [u8]) -> usize {
+        if buf.is_empty() {
+            return 0;
+        }
+        return self.read(buf).await;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Read {
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    #[async_fn(provided, state_machine)]
+    async fn read_twice(&mut self, buf: &mut [u8]) -> usize {
+        let first = self.read(buf).await;
+        let limit = 2 * first;
+        let second = self.read(buf).await;
+        limit.min(first + second)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use async_trait_ext::async_trait_ext;
+
+#[async_trait_ext]
+trait Write {
+    async fn write(&mut self, bytes: &[u8]);
+
+    #[async_fn(provided, take_args)]
+    async fn write_vec(&mut self, bytes: Vec<u8>) {
+        self.write(&bytes).await;
+    }
+}
+
+fn main() {}
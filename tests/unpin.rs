@@ -0,0 +1,31 @@
+use async_trait_ext::async_trait_ext;
+use std::{
+    future::Future,
+    marker::PhantomPinned,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+#[async_trait_ext]
+pub trait Sink {
+    async fn send<T: Copy>(&self, item: T) -> bool;
+}
+
+struct Discard;
+
+impl Sink for Discard {
+    fn poll_send<T: Copy>(&self, _: T, _: &mut Context) -> Poll<bool> {
+        Poll::Ready(true)
+    }
+}
+
+fn assert_unpin<T: Unpin>(_: &T) {}
+
+#[test]
+fn test_unpin() {
+    let mut future = Discard.send(PhantomPinned);
+    assert_unpin(&future);
+
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready(true));
+}